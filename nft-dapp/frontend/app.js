@@ -13,6 +13,9 @@ const ABI = [
       { internalType: "string", name: "symbol", type: "string" },
       { internalType: "string", name: "base_uri", type: "string" },
       { internalType: "uint256", name: "max_supply", type: "uint256" },
+      { internalType: "uint256", name: "start_id", type: "uint256" },
+      { internalType: "address", name: "platform_fee_recipient", type: "address" },
+      { internalType: "uint256", name: "platform_fee_bps", type: "uint256" },
     ],
     name: "init",
     outputs: [],
@@ -26,6 +29,13 @@ const ABI = [
     stateMutability: "view",
     type: "function",
   },
+  {
+    inputs: [],
+    name: "transferFee",
+    outputs: [{ internalType: "uint256", name: "", type: "uint256" }],
+    stateMutability: "view",
+    type: "function",
+  },
   {
     inputs: [],
     name: "getOwner",
@@ -65,7 +75,7 @@ const ABI = [
     ],
     name: "safeTransferFrom",
     outputs: [],
-    stateMutability: "nonpayable",
+    stateMutability: "payable",
     type: "function",
   },
   {
@@ -77,7 +87,7 @@ const ABI = [
     ],
     name: "safeTransferFrom",
     outputs: [],
-    stateMutability: "nonpayable",
+    stateMutability: "payable",
     type: "function",
   },
   {
@@ -88,7 +98,7 @@ const ABI = [
     ],
     name: "transferFrom",
     outputs: [],
-    stateMutability: "nonpayable",
+    stateMutability: "payable",
     type: "function",
   },
   {
@@ -170,6 +180,9 @@ const initNameInput = document.getElementById("initName")
 const initSymbolInput = document.getElementById("initSymbol")
 const initBaseUriInput = document.getElementById("initBaseUri")
 const initMaxSupplyInput = document.getElementById("initMaxSupply")
+const initStartIdInput = document.getElementById("initStartId")
+const initPlatformFeeRecipientInput = document.getElementById("initPlatformFeeRecipient")
+const initPlatformFeeBpsInput = document.getElementById("initPlatformFeeBps")
 const uploadForm = document.getElementById("uploadForm")
 const nftNameInput = document.getElementById("nftName")
 const nftDescriptionInput = document.getElementById("nftDescription")
@@ -343,6 +356,9 @@ async function handleInit(event) {
   const symbol = initSymbolInput.value.trim()
   const baseUri = initBaseUriInput.value.trim()
   const maxSupply = parseInt(initMaxSupplyInput.value)
+  const startId = parseInt(initStartIdInput.value) || 0
+  const platformFeeRecipient = initPlatformFeeRecipientInput.value.trim() || ethers.ZeroAddress
+  const platformFeeBps = parseInt(initPlatformFeeBpsInput.value) || 0
 
   // Input validation
   if (!name || !symbol || !baseUri) {
@@ -366,7 +382,7 @@ async function handleInit(event) {
       throw new Error("Please connect your wallet first")
     }
 
-    const tx = await contract.init(name, symbol, baseUri, maxSupply)
+    const tx = await contract.init(name, symbol, baseUri, maxSupply, startId, platformFeeRecipient, platformFeeBps)
     console.log("Initialization transaction sent:", tx.hash)
     await tx.wait()
 
@@ -593,7 +609,8 @@ async function handleTransfer(event) {
     transferBtn.disabled = true
     transferBtn.textContent = "Transferring..."
 
-    const tx = await contract.transferFrom(fromAddress, toAddress, currentTransferTokenId)
+    const transferFee = await contract.transferFee()
+    const tx = await contract.transferFrom(fromAddress, toAddress, currentTransferTokenId, { value: transferFee })
     console.log("Transfer transaction sent:", tx.hash)
     await tx.wait()
 
@@ -3,18 +3,247 @@
 
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 #![cfg_attr(not(any(test, feature = "export-abi")), no_std)]
+#![recursion_limit = "512"]
 extern crate alloc;
 
+use alloc::format;
 use alloc::vec;
 use alloc::vec::Vec;
 use alloc::string::String;
-use openzeppelin_stylus::token::erc721::{self, Erc721, IErc721, extensions::IErc721Metadata};
+use alloy_sol_types::{sol, SolValue};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use openzeppelin_stylus::token::erc721::{
+    self, extensions::IErc721Metadata, Approval, ERC721IncorrectOwner, ERC721InvalidApprover,
+    ERC721InvalidOwner, ERC721InvalidReceiver, ERC721InvalidSender, Erc721, IErc721, Transfer,
+};
+use openzeppelin_stylus::utils::cryptography::{ecdsa, eip712::IEip712};
 use openzeppelin_stylus::utils::introspection::erc165::IErc165;
 use stylus_sdk::abi::Bytes;
-use stylus_sdk::alloy_primitives::{Address, FixedBytes, U256};
+use stylus_sdk::alloy_primitives::{address, b256, hex, Address, FixedBytes, B256, U256};
+use stylus_sdk::crypto::keccak;
+use stylus_sdk::keccak_const::Keccak256;
 use stylus_sdk::prelude::*;
-use stylus_sdk::storage::{StorageAddress, StorageU256, StorageString};
-use stylus_sdk::msg;
+use stylus_sdk::storage::{StorageAddress, StorageB256, StorageBool, StorageMap, StorageU256, StorageU8, StorageString, StorageVec};
+
+/// Partner tokens redeemed through `claim_by_burning` are sent here, since most
+/// ERC-721 contracts don't expose a public `burn`.
+const BURN_ADDRESS: Address = address!("000000000000000000000000000000000000dEaD");
+
+/// Storage layout version written by this code. Bump it whenever a release needs
+/// a migration of existing storage.
+const STORAGE_VERSION: u64 = 1;
+
+/// `metadata_mode` values: token URIs come from storage (or the renderer), are
+/// generated on-chain as a JSON data URI embedding an SVG image, or as the raw SVG.
+const METADATA_MODE_STORED: u8 = 0;
+const METADATA_MODE_JSON: u8 = 1;
+const METADATA_MODE_SVG: u8 = 2;
+
+/// Code hash of an account without code.
+const EMPTY_CODE_HASH: B256 = b256!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470");
+
+/// EIP-712 type hash of the `PermitForAll` message signed for `permit_for_all`.
+const PERMIT_FOR_ALL_TYPEHASH: [u8; 32] = Keccak256::new()
+    .update(b"PermitForAll(address owner,address operator,bool approved,uint256 nonce,uint256 deadline)")
+    .finalize();
+
+/// EIP-712 type hash of the `PermitBatch` message signed for `permit_batch`.
+const PERMIT_BATCH_TYPEHASH: [u8; 32] = Keccak256::new()
+    .update(b"PermitBatch(address owner,address operator,uint256[] tokenIds,uint256 nonce,uint256 deadline)")
+    .finalize();
+
+/// EIP-712 type hash of the `Voucher` message signed for `redeem`.
+const VOUCHER_TYPEHASH: [u8; 32] = Keccak256::new()
+    .update(b"Voucher(address to,string uri,uint256 nonce)")
+    .finalize();
+
+/// EIP-712 type hash of the `AllowlistMint` message signed for `signed_allowlist_mint`.
+const ALLOWLIST_MINT_TYPEHASH: [u8; 32] = Keccak256::new()
+    .update(b"AllowlistMint(address minter)")
+    .finalize();
+
+/// Every interface id `supports_interface` returns true for.
+const DECLARED_INTERFACES: [[u8; 4]; 6] = [
+    // ERC-165
+    [0x01, 0xff, 0xc9, 0xa7],
+    // ERC-721
+    [0x80, 0xac, 0x58, 0xcd],
+    // ERC-721 Metadata
+    [0x5b, 0x5e, 0x13, 0x9f],
+    // ERC-4906 metadata update events
+    [0x49, 0x06, 0x49, 0x06],
+    // ERC-2981 royalties
+    [0x2a, 0x55, 0x20, 0x5a],
+    // ERC-721 receiver
+    [0x15, 0x0b, 0x7a, 0x02],
+];
+
+/// Largest cut of paid mints a launch platform can take, in basis points.
+const MAX_PLATFORM_FEE_BPS: u64 = 1_000;
+
+/// Kinds of `Action`. Once a multisig is set up, these only run through
+/// `execute_action`: `withdraw_to(target)`, `apply_max_supply()`, `freeze_base_uri()`,
+/// `burn_unminted_reserved()`, `drain(target)`, `reveal_and_freeze(uri)` and
+/// `withdraw_erc20(token, target)`. The last two are proposed with
+/// `propose_reveal_and_freeze` and `propose_erc20_withdrawal`.
+const ACTION_WITHDRAW: u8 = 0;
+const ACTION_APPLY_MAX_SUPPLY: u8 = 1;
+const ACTION_FREEZE_BASE_URI: u8 = 2;
+const ACTION_FINALIZE_SUPPLY: u8 = 3;
+const ACTION_DRAIN: u8 = 4;
+const ACTION_REVEAL_AND_FREEZE: u8 = 5;
+const ACTION_WITHDRAW_ERC20: u8 = 6;
+
+/// Seconds a proposed `max_supply` waits before it can be applied.
+const MAX_SUPPLY_TIMELOCK: u64 = 2 * 24 * 60 * 60;
+
+/// Largest batch accepted by batch functions until the owner changes it.
+const DEFAULT_MAX_BATCH_SIZE: u64 = 50;
+
+/// Longest token URI in bytes accepted until the owner changes it.
+const DEFAULT_MAX_URI_LENGTH: u64 = 300;
+
+sol_interface! {
+    interface IRedemptionCollection {
+        function ownerOf(uint256 token_id) external view returns (address);
+        function transferFrom(address from, address to, uint256 token_id) external;
+    }
+
+    interface IMetadataRenderer {
+        function tokenURI(uint256 token_id) external view returns (string);
+    }
+
+    interface ITransferValidator {
+        function canTransfer(address from, address to, uint256 token_id) external view returns (bool);
+    }
+
+    interface IOperatorFilterRegistry {
+        function registerAndSubscribe(address registrant, address subscription) external;
+        function isOperatorAllowed(address registrant, address operator) external view returns (bool);
+    }
+
+    interface IPriceOracle {
+        function latestPrice() external view returns (uint256);
+    }
+
+    interface IErc20 {
+        function balanceOf(address account) external view returns (uint256);
+        function transfer(address to, uint256 value) external returns (bool);
+    }
+}
+
+sol! {
+    /// Emitted whenever ETH leaves the contract through a withdrawal.
+    #[derive(Debug)]
+    event EthWithdrawn(address indexed to, uint256 amount);
+    /// Emitted whenever ERC-20 tokens leave the contract through a withdrawal.
+    #[derive(Debug)]
+    event Erc20Withdrawn(address indexed token, address indexed to, uint256 amount);
+
+    /// ERC-4906: metadata of a range of tokens changed and should be re-fetched.
+    #[derive(Debug)]
+    event BatchMetadataUpdate(uint256 _fromTokenId, uint256 _toTokenId);
+    /// ERC-4906: metadata of a single token changed and should be re-fetched.
+    #[derive(Debug)]
+    event MetadataUpdate(uint256 _tokenId);
+
+    /// The collection was revealed, with `base_uri` as the base URI from then on.
+    #[derive(Debug)]
+    event Revealed(string base_uri);
+
+    /// The account signing allowlist mints changed. Signatures from `old_signer` stop working.
+    #[derive(Debug)]
+    event AllowSignerRotated(address indexed old_signer, address indexed new_signer);
+
+    /// A quarter of `max_supply` was minted for the first time. `percentBps` is 2500,
+    /// 5000, 7500 or 10000.
+    #[derive(Debug)]
+    event SupplyMilestone(uint256 percentBps, uint256 minted);
+
+    /// The default royalty receiver and share changed.
+    #[derive(Debug)]
+    event DefaultRoyaltySet(address indexed receiver, uint256 bps);
+    /// `tokenId` got its own royalty receiver and share.
+    #[derive(Debug)]
+    event TokenRoyaltySet(uint256 indexed tokenId, address indexed receiver, uint256 bps);
+    /// `tokenId` went back to the default royalty.
+    #[derive(Debug)]
+    event TokenRoyaltyReset(uint256 indexed tokenId);
+
+    /// The collection was closed at `totalSupply` tokens by `burn_unminted_reserved`.
+    #[derive(Debug)]
+    event CollectionFinalized(uint256 totalSupply);
+
+    /// The owner cleared the per-token feature state of `tokenId` with `reset_token_state`.
+    #[derive(Debug)]
+    event TokenStateReset(uint256 tokenId);
+
+    /// The mint would exceed `max_supply`. `minted` is the number of tokens minted so far.
+    #[derive(Debug)]
+    error MaxSupplyReached(uint256 minted, uint256 cap);
+    /// `id` was never minted or has been burned.
+    #[derive(Debug)]
+    error NonexistentToken(uint256 id);
+}
+
+/// Typed errors returned by `DEMONFT`, so clients can decode them instead of matching strings.
+#[derive(SolidityError, Debug)]
+pub enum DemoNftError {
+    MaxSupplyReached(MaxSupplyReached),
+    NonexistentToken(NonexistentToken),
+}
+
+/// A token held by the contract on behalf of the account that staked it.
+#[storage]
+pub struct StakeInfo {
+    staker: StorageAddress,
+    staked_at: StorageU256,
+    rewards_from: StorageU256,
+    // Position of the token in its staker's `staked_tokens` list.
+    list_index: StorageU256,
+}
+
+/// A royalty that replaces the default one for a single token.
+#[storage]
+pub struct TokenRoyalty {
+    receiver: StorageAddress,
+    bps: StorageU256,
+}
+
+/// What was paid for a token minted through a paid mint, for `refund`. `paid` is what
+/// the contract kept after the platform fee, and `forwarded` is set once that went on
+/// to the treasury.
+#[storage]
+pub struct Purchase {
+    buyer: StorageAddress,
+    paid: StorageU256,
+    minted_at: StorageU256,
+    forwarded: StorageBool,
+}
+
+/// An allowlist tier with its own Merkle root of allowed minters, price per token and
+/// per-wallet cap.
+#[storage]
+pub struct Tier {
+    root: StorageB256,
+    price: StorageU256,
+    cap: StorageU256,
+    minted: StorageMap<Address, StorageU256>,
+}
+
+/// A sensitive action proposed to the multisig signers, run by `execute_action` once
+/// enough of them confirmed it.
+#[storage]
+pub struct Action {
+    proposed: StorageBool,
+    executed: StorageBool,
+    kind: StorageU8,
+    target: StorageAddress,
+    confirmations: StorageU256,
+    confirmed_by: StorageMap<Address, StorageBool>,
+    token: StorageAddress,
+    uri: StorageString,
+}
 
 #[entrypoint]
 #[storage]
@@ -26,28 +255,283 @@ pub struct DEMONFT {
     name: StorageString,
     symbol: StorageString,
     base_uri: StorageString,
+    // Legacy newline separated URI list, indexed by mint order. Only read as a
+    // fallback now that URIs are stored per token in `token_uris`.
     token_uris_str: StorageString,
+    // ERC721A-style ownership: only the first id of a batch run has its owner
+    // written, every other id resolves to the nearest recorded owner below it.
+    owners: StorageMap<U256, StorageAddress>,
+    balances: StorageMap<Address, StorageU256>,
+    redemption_collection: StorageAddress,
+    stakes: StorageMap<U256, StakeInfo>,
+    reward_token: StorageAddress,
+    reward_rate_per_second: StorageU256,
+    metadata_renderer: StorageAddress,
+    token_uris: StorageMap<U256, StorageString>,
+    token_price: StorageMap<U256, StorageU256>,
+    minted_count: StorageU256,
+    max_batch_size: StorageU256,
+    revealed: StorageBool,
+    reveal_at: StorageU256,
+    unrevealed_uri: StorageString,
+    mint_credits: StorageMap<Address, StorageU256>,
+    storage_version: StorageU256,
+    fee_token: StorageAddress,
+    reserved_remaining: StorageU256,
+    append_version_query: StorageBool,
+    free_allocation: StorageMap<Address, StorageU256>,
+    staked_tokens: StorageMap<Address, StorageVec<StorageU256>>,
+    block_mints: StorageMap<U256, StorageU256>,
+    max_mints_per_block: StorageU256,
+    start_id: StorageU256,
+    permit_nonces: StorageMap<Address, StorageU256>,
+    voucher_signer: StorageAddress,
+    used_vouchers: StorageMap<FixedBytes<32>, StorageBool>,
+    royalty_receiver: StorageAddress,
+    royalty_bps: StorageU256,
+    flat_royalty: StorageBool,
+    flat_fee: StorageU256,
+    // Inverted so that contract recipients stay allowed by default.
+    block_contract_recipients: StorageBool,
+    paused: StorageBool,
+    pause_expiry: StorageU256,
+    metadata_mode: StorageU8,
+    token_royalties: StorageMap<U256, TokenRoyalty>,
+    custom_royalty_count: StorageU256,
+    uri_suffix: StorageString,
+    base_uri_frozen: StorageBool,
+    transfer_validator: StorageAddress,
+    ipfs_gateway: StorageString,
+    public_price: StorageU256,
+    allowlist: StorageMap<Address, StorageBool>,
+    allowlist_discount_bps: StorageU256,
+    treasury: StorageAddress,
+    forward_on_mint: StorageBool,
+    // Burned ids keep a zero owner but must not resolve to their run's owner.
+    burned: StorageMap<U256, StorageBool>,
+    has_minted: StorageMap<Address, StorageBool>,
+    buyback_amount: StorageU256,
+    highest_id: StorageU256,
+    edition_supply: StorageMap<U256, StorageU256>,
+    edition_minted: StorageMap<U256, StorageU256>,
+    token_edition: StorageMap<U256, StorageU256>,
+    enforce_unique_uri: StorageBool,
+    seen_uri_hashes: StorageMap<FixedBytes<32>, StorageBool>,
+    allow_signer: StorageAddress,
+    signed_allowlist_used: StorageMap<Address, StorageBool>,
+    spent_by: StorageMap<Address, StorageU256>,
+    max_spend_per_wallet: StorageU256,
+    refund_window: StorageU256,
+    purchases: StorageMap<U256, Purchase>,
+    auto_approve_operator: StorageAddress,
+    auto_approve: StorageBool,
+    // Royalties paid in through `pay_royalty` that their receivers haven't claimed yet.
+    // Kept apart from mint proceeds, so withdrawals leave them in the contract.
+    pending_royalties: StorageMap<Address, StorageU256>,
+    total_pending_royalties: StorageU256,
+    // Every operator an owner approved for all since their last `revoke_all_approvals`,
+    // with a flag per operator to keep the list free of duplicates.
+    approved_operators: StorageMap<Address, StorageVec<StorageAddress>>,
+    operator_tracked: StorageMap<Address, StorageMap<Address, StorageBool>>,
+    price_oracle: StorageAddress,
+    price_usd_cents: StorageU256,
+    platform_fee_recipient: StorageAddress,
+    platform_fee_bps: StorageU256,
+    burned_count: StorageU256,
+    min_hold_seconds: StorageU256,
+    acquired_at: StorageMap<U256, StorageU256>,
+    // Per-owner token lists for enumeration, with each token's position in its owner's
+    // list so removal can swap and pop.
+    owned_tokens: StorageMap<Address, StorageVec<StorageU256>>,
+    owned_index: StorageMap<U256, StorageU256>,
+    pending_max_supply: StorageU256,
+    max_supply_unlock_at: StorageU256,
+    snapshot_id: StorageU256,
+    snapshot_blocks: StorageMap<U256, StorageU256>,
+    snapshot_balances: StorageMap<U256, StorageMap<Address, StorageU256>>,
+    snapshot_recorded: StorageMap<U256, StorageMap<Address, StorageBool>>,
+    max_uri_length: StorageU256,
+    supply_milestones: StorageU8,
+    operator_filter_registry: StorageAddress,
+    operator_filter_enabled: StorageBool,
+    minted_at: StorageMap<U256, StorageU256>,
+    royalty_follows_owner: StorageBool,
+    multisig_signers: StorageVec<StorageAddress>,
+    is_multisig_signer: StorageMap<Address, StorageBool>,
+    multisig_threshold: StorageU256,
+    action_nonce: StorageU256,
+    actions: StorageMap<FixedBytes<32>, Action>,
+    lock_until: StorageMap<U256, StorageU256>,
+    tiers: StorageMap<U256, Tier>,
+    transfer_fee: StorageU256,
+    token_seed: StorageMap<U256, StorageB256>,
+    mint_cooldown: StorageU256,
+    last_mint_at: StorageMap<Address, StorageU256>,
+    royalties_frozen: StorageBool,
+    auction_start_price: StorageU256,
+    auction_end_price: StorageU256,
+    auction_start: StorageU256,
+    auction_duration: StorageU256,
+    token_tips: StorageMap<U256, StorageU256>,
+    minted_explicitly: StorageMap<U256, StorageBool>,
+    run_breaks: StorageMap<U256, StorageBool>,
+    receiver_allowlist_enabled: StorageBool,
+    allowed_receivers: StorageMap<Address, StorageBool>,
+    allowlist_claims: StorageMap<Address, StorageU256>,
+    // Whether an address's balance from before ownership moved out of `erc721` has been
+    // added to `balances`.
+    legacy_balance_moved: StorageMap<Address, StorageBool>,
 }
 
 #[public]
+#[implements(IErc721<Error = Vec<u8>>, IErc721Metadata<Error = Vec<u8>>, IErc165)]
 impl DEMONFT {
-    /// Initializes the contract. Sets the deployer as owner, name, symbol, base_uri, and max supply.
-    pub fn init(&mut self, name: String, symbol: String, base_uri: String, max_supply: U256) {
+    /// Initializes the contract. Sets the deployer as owner, name, symbol, base_uri, max supply,
+    /// the id sequential minting starts from, and the launch platform's cut of paid mints,
+    /// which can't be changed afterwards. The name and symbol can't be empty, and the base
+    /// URI can't be longer than `DEFAULT_MAX_URI_LENGTH`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn init(
+        &mut self,
+        name: String,
+        symbol: String,
+        base_uri: String,
+        max_supply: U256,
+        start_id: U256,
+        platform_fee_recipient: Address,
+        platform_fee_bps: U256,
+    ) {
         // Only allow init once
         if self.owner.get() != Address::ZERO {
             panic!("Already initialized");
         }
-        self.owner.set(msg::sender());
+        if platform_fee_bps > U256::from(MAX_PLATFORM_FEE_BPS) {
+            panic!("Platform fee too high");
+        }
+        if !platform_fee_bps.is_zero() && platform_fee_recipient.is_zero() {
+            panic!("No platform fee recipient");
+        }
+        if name.is_empty() || symbol.is_empty() {
+            panic!("Empty name/symbol");
+        }
+        if base_uri.len() as u64 > DEFAULT_MAX_URI_LENGTH {
+            panic!("URI too long");
+        }
+        self.platform_fee_recipient.set(platform_fee_recipient);
+        self.platform_fee_bps.set(platform_fee_bps);
+        self.owner.set(self.vm().msg_sender());
         self.name.set_str(name.as_str());
         self.symbol.set_str(symbol.as_str());
         self.base_uri.set_str(base_uri.as_str());
         self.max_supply.set(max_supply);
-        self.next_id.set(U256::ZERO);
+        self.start_id.set(start_id);
+        self.next_id.set(start_id);
+        self.max_batch_size.set(U256::from(DEFAULT_MAX_BATCH_SIZE));
+        self.max_uri_length.set(U256::from(DEFAULT_MAX_URI_LENGTH));
+        self.storage_version.set(U256::from(STORAGE_VERSION));
+    }
+
+    /// Returns the launch platform's fee recipient and its cut of paid mints in basis
+    /// points, both fixed at init.
+    pub fn platform_fee(&self) -> (Address, U256) {
+        (self.platform_fee_recipient.get(), self.platform_fee_bps.get())
+    }
+
+    /// Returns the storage layout version. Zero for deployments from before versioning.
+    pub fn storage_version(&self) -> u64 {
+        self.storage_version.get().to::<u64>()
+    }
+
+    /// Records that storage was migrated to `version`. It can only move forward, and
+    /// never past the layout this code knows about. Moving a deployment from before
+    /// versioning carries its mint counter over into `total_minted`.
+    pub fn set_storage_version(&mut self, version: u64) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if version <= self.storage_version() || version > STORAGE_VERSION {
+            return Err(b"Invalid storage version".to_vec());
+        }
+        // Before versioning `next_id` counted every token ever minted, all of them
+        // sequential from zero.
+        let legacy_minted = self.next_id.get();
+        if self.storage_version() == 0 && self.minted_count.get().is_zero() && !legacy_minted.is_zero() {
+            self.minted_count.set(legacy_minted);
+            self.highest_id.set(legacy_minted - U256::from(1));
+        }
+        self.storage_version.set(U256::from(version));
+        Ok(())
     }
 
     /// Returns current total minted tokens.
     pub fn total_minted(&self) -> U256 {
-        self.next_id.get()
+        self.minted_count.get()
+    }
+
+    /// Returns true while minting and transfers are paused, either until `unpause`
+    /// or until the expiry set by `pause_until` passes.
+    pub fn paused(&self) -> bool {
+        self.paused.get() || U256::from(self.vm().block_timestamp()) < self.pause_expiry.get()
+    }
+
+    /// Returns when a `pause_until` pause lifts by itself, zero when none is set.
+    pub fn pause_expiry(&self) -> U256 {
+        self.pause_expiry.get()
+    }
+
+    /// Pauses minting and transfers until `unpause` is called.
+    pub fn pause(&mut self) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.paused.set(true);
+        Ok(())
+    }
+
+    /// Pauses minting and transfers until `timestamp`, after which they resume on their own.
+    pub fn pause_until(&mut self, timestamp: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.pause_expiry.set(timestamp);
+        Ok(())
+    }
+
+    /// Lifts any pause right away, timed or not.
+    pub fn unpause(&mut self) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.paused.set(false);
+        self.pause_expiry.set(U256::ZERO);
+        Ok(())
+    }
+
+    /// Returns the id the next sequential mint will get.
+    pub fn next_token_id(&self) -> U256 {
+        self._next_sequential_id(self.next_id.get())
+    }
+
+    /// Moves the sequential counter forward to `new_next`, past ids minted with an
+    /// explicit id. Ids it skips over can still be minted explicitly.
+    pub fn set_next_id(&mut self, new_next: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        let next = self.next_id.get();
+        if new_next < next {
+            return Err(b"Counter moves forward only".to_vec());
+        }
+        if !self.minted_count.get().is_zero() && new_next <= self.highest_id.get() {
+            return Err(b"Below highest id".to_vec());
+        }
+        // Skipped ids belong to no run, so stop owner lookups from walking back past them.
+        let gap_start = self._next_sequential_id(next);
+        if gap_start < new_next {
+            self.run_breaks.setter(gap_start).set(true);
+        }
+        self.next_id.set(new_next);
+        Ok(())
+    }
+
+    /// Returns the largest id ever minted by any mint path, zero before the first mint.
+    pub fn highest_minted_id(&self) -> U256 {
+        self.highest_id.get()
+    }
+
+    /// Returns the id sequential minting started from.
+    pub fn start_id(&self) -> U256 {
+        self.start_id.get()
     }
 
     /// Returns the contract owner.
@@ -57,119 +541,5602 @@ impl DEMONFT {
 
     /// Mints a new token to the specified address. Requires IPFS URI for token metadata.
     pub fn mint(&mut self, to: Address, uri: String) -> Result<U256, Vec<u8>> {
-        let token_id = self.next_id.get();
-        let supply_cap = self.max_supply.get();
-        if supply_cap != U256::ZERO && token_id >= supply_cap {
-            return Err(b"Max supply reached".to_vec());
+        self._mint_one(to, &uri)
+    }
+
+    /// Returns how many seconds a wallet must wait between mints. Zero means no cooldown.
+    pub fn mint_cooldown(&self) -> U256 {
+        self.mint_cooldown.get()
+    }
+
+    /// Sets how many seconds a wallet must wait between mints. The owner is exempt.
+    pub fn set_mint_cooldown(&mut self, seconds: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.mint_cooldown.set(seconds);
+        Ok(())
+    }
+
+    /// Returns how many tokens can be minted per block. Zero means no limit.
+    pub fn max_mints_per_block(&self) -> U256 {
+        self.max_mints_per_block.get()
+    }
+
+    /// Sets how many tokens can be minted per block. Zero removes the limit.
+    pub fn set_max_mints_per_block(&mut self, cap: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.max_mints_per_block.set(cap);
+        Ok(())
+    }
+
+    /// Returns how many tokens were minted in the current block while a cap was set.
+    pub fn mints_this_block(&self) -> U256 {
+        self.block_mints.get(U256::from(self.vm().block_number()))
+    }
+
+    /// Returns the most tokens that can ever be minted. Zero means no limit.
+    pub fn max_supply(&self) -> U256 {
+        self.max_supply.get()
+    }
+
+    /// Returns true once `max_supply` tokens have been minted. An uncapped collection
+    /// never sells out. Counts tokens rather than ids, so `start_id` doesn't matter.
+    pub fn is_sold_out(&self) -> bool {
+        let max_supply = self.max_supply.get();
+        !max_supply.is_zero() && self.minted_count.get() >= max_supply
+    }
+
+    /// Returns the proposed `max_supply` and when it can be applied, both zero when
+    /// nothing is proposed.
+    pub fn pending_max_supply(&self) -> (U256, U256) {
+        (self.pending_max_supply.get(), self.max_supply_unlock_at.get())
+    }
+
+    /// Proposes a new `max_supply`, which `apply_max_supply` can set once
+    /// `MAX_SUPPLY_TIMELOCK` has passed. Replaces any earlier proposal.
+    pub fn propose_max_supply(&mut self, new_max: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_max_supply(new_max)?;
+        let unlock_at = U256::from(self.vm().block_timestamp()) + U256::from(MAX_SUPPLY_TIMELOCK);
+        self.pending_max_supply.set(new_max);
+        self.max_supply_unlock_at.set(unlock_at);
+        Ok(())
+    }
+
+    /// Sets `max_supply` to the proposed value once its timelock has passed.
+    pub fn apply_max_supply(&mut self) -> Result<(), Vec<u8>> {
+        self._only_owner_without_multisig()?;
+        self._apply_max_supply()
+    }
+
+    /// Returns how many of the reserved team tokens are still unminted.
+    pub fn reserved_remaining(&self) -> U256 {
+        self.reserved_remaining.get()
+    }
+
+    /// Reserves `amount` of the remaining supply for `mint_reserved`, replacing the
+    /// previous reserve. Other mints stop early enough to leave it free.
+    pub fn set_reserved(&mut self, amount: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        let max_supply = self.max_supply.get();
+        if !max_supply.is_zero() && self.minted_count.get() + amount > max_supply {
+            return Err(b"Reserve exceeds supply".to_vec());
+        }
+        self.reserved_remaining.set(amount);
+        Ok(())
+    }
+
+    /// Mints one of the reserved tokens to `to`.
+    pub fn mint_reserved(&mut self, to: Address, uri: String) -> Result<U256, Vec<u8>> {
+        self._only_owner()?;
+        let reserved = self.reserved_remaining.get();
+        if reserved.is_zero() {
+            return Err(b"No reserve left".to_vec());
         }
+        // Releasing the slot first lets the regular cap check account for it.
+        self.reserved_remaining.set(reserved - U256::from(1));
+        self._mint_one(to, &uri)
+    }
+
+    /// Closes the collection at its current supply: drops the unminted reserve and sets
+    /// `max_supply` to the number of tokens minted so far, cancelling any proposed change.
+    /// That is the only value it can set, and no more tokens can be minted under it, so
+    /// it skips the `propose_max_supply` timelock. Runs through the multisig once set up.
+    pub fn burn_unminted_reserved(&mut self) -> Result<(), Vec<u8>> {
+        self._only_owner_without_multisig()?;
+        self._finalize_supply()
+    }
+
+    /// Returns how many mints `who` can still redeem with `mint_with_credit`.
+    pub fn credits_of(&self, who: Address) -> U256 {
+        self.mint_credits.get(who)
+    }
 
-        // Call underlying Erc721 mint (unsafe, assume exists)
-        self.erc721._mint(to, token_id)?;
+    /// Grants `amount` more mint credits to `to`.
+    pub fn grant_credits(&mut self, to: Address, amount: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        let credits = self.mint_credits.get(to);
+        self.mint_credits.setter(to).set(credits + amount);
+        Ok(())
+    }
 
-        let mut current = self.token_uris_str.get_string();
-        if !current.is_empty() {
-            current.push('\n');
+    /// Spends one of the caller's mint credits to mint a token to them.
+    pub fn mint_with_credit(&mut self, uri: String) -> Result<U256, Vec<u8>> {
+        let sender = self.vm().msg_sender();
+        let credits = self.mint_credits.get(sender);
+        if credits.is_zero() {
+            return Err(b"No credits".to_vec());
         }
-        current.push_str(&uri);
-        self.token_uris_str.set_str(current.as_str());
+        self.mint_credits.setter(sender).set(credits - U256::from(1));
+        self._mint_one(sender, &uri)
+    }
 
-        self.next_id.set(token_id + U256::from(1));
+    /// Returns how many free mints `who` has left.
+    pub fn free_allocation(&self, who: Address) -> U256 {
+        self.free_allocation.get(who)
+    }
+
+    /// Sets how many free mints `who` has left.
+    pub fn set_free_allocation(&mut self, who: Address, amount: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.free_allocation.setter(who).set(amount);
+        Ok(())
+    }
+
+    /// Uses one of the caller's free mints to mint a token to them.
+    pub fn free_mint(&mut self, uri: String) -> Result<U256, Vec<u8>> {
+        let sender = self.vm().msg_sender();
+        let allocation = self.free_allocation.get(sender);
+        if allocation.is_zero() {
+            return Err(b"No free mints".to_vec());
+        }
+        self.free_allocation.setter(sender).set(allocation - U256::from(1));
+        self._mint_one(sender, &uri)
+    }
+
+    /// Returns the price of a `public_mint`.
+    pub fn public_price(&self) -> U256 {
+        self.public_price.get()
+    }
+
+    /// Sets the price of a `public_mint`. Allowlist mints are discounted from it.
+    pub fn set_public_price(&mut self, price: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.public_price.set(price);
+        Ok(())
+    }
+
+    /// Returns the USD price oracle and the public mint price in US cents. While both
+    /// are set, `public_mint` is priced in USD instead of `public_price`.
+    pub fn usd_price(&self) -> (Address, U256) {
+        (self.price_oracle.get(), self.price_usd_cents.get())
+    }
+
+    /// Prices `public_mint` at `price_usd_cents`, converted to ETH through `oracle` at
+    /// mint time. A zero oracle or price switches back to `public_price`.
+    pub fn set_usd_price(&mut self, oracle: Address, price_usd_cents: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.price_oracle.set(oracle);
+        self.price_usd_cents.set(price_usd_cents);
+        Ok(())
+    }
+
+    /// Returns the ETH `public_mint` currently requires. The oracle's `latestPrice` is
+    /// the USD price of one ETH in cents.
+    pub fn public_mint_price(&self) -> Result<U256, Vec<u8>> {
+        let oracle = self.price_oracle.get();
+        let price_usd_cents = self.price_usd_cents.get();
+        if oracle.is_zero() || price_usd_cents.is_zero() {
+            return Ok(self.public_price.get());
+        }
+        let eth_usd_cents = IPriceOracle::new(oracle).latest_price(self).map_err(|_| b"Bad oracle".to_vec())?;
+        if eth_usd_cents.is_zero() {
+            return Err(b"Bad oracle".to_vec());
+        }
+        // Round up, so the payment never falls short of the USD price.
+        let wei = price_usd_cents * U256::from(10).pow(U256::from(18));
+        Ok(wei.div_ceil(eth_usd_cents))
+    }
+
+    /// Returns what `who` pays for their next mint: nothing while they have free mints or
+    /// credits left, the allowlist price when allowlisted, otherwise the public price.
+    pub fn effective_price_for(&self, who: Address) -> Result<U256, Vec<u8>> {
+        if !self.free_allocation.get(who).is_zero() || !self.mint_credits.get(who).is_zero() {
+            return Ok(U256::ZERO);
+        }
+        if self.allowlist.get(who) {
+            return self.allowlist_price();
+        }
+        self.public_mint_price()
+    }
+
+    /// Returns whether `who` could mint `quantity` tokens through `public_mint` right now,
+    /// and the reason when they couldn't.
+    pub fn can_mint(&self, who: Address, quantity: U256) -> (bool, String) {
+        match self._mint_blocker(who, quantity) {
+            Some(reason) => (false, reason.into()),
+            None => (true, String::new()),
+        }
+    }
+
+    /// Returns how many more tokens `who` can mint: the public mints left under
+    /// `max_spend_per_wallet` plus free mints and credits, never more than the unreserved
+    /// supply left.
+    pub fn remaining_allowance(&self, who: Address) -> U256 {
+        let max_supply = self.max_supply.get();
+        let supply_left = if max_supply.is_zero() {
+            U256::MAX
+        } else {
+            max_supply
+                .saturating_sub(self.reserved_remaining.get())
+                .saturating_sub(self.minted_count.get())
+        };
+
+        let spend_cap = self.max_spend_per_wallet.get();
+        let paid_left = match self.public_mint_price() {
+            Ok(price) if !spend_cap.is_zero() && !price.is_zero() => {
+                spend_cap.saturating_sub(self.spent_by.get(who)) / price
+            }
+            Ok(_) => U256::MAX,
+            Err(_) => U256::ZERO,
+        };
+
+        paid_left
+            .saturating_add(self.free_allocation.get(who))
+            .saturating_add(self.mint_credits.get(who))
+            .min(supply_left)
+    }
+
+    /// Mints a token to the caller for `public_mint_price`. Any payment above the price
+    /// stays in the contract.
+    #[payable]
+    pub fn public_mint(&mut self, uri: String) -> Result<U256, Vec<u8>> {
+        let payment = self._take_payment(self.public_mint_price()?)?;
+        let forwarded = self.forward_on_mint.get() && !payment.is_zero();
+        if forwarded {
+            self.vm().transfer_eth(self.treasury.get(), payment)?;
+        }
+        let sender = self.vm().msg_sender();
+        let token_id = self._mint_one(sender, &uri)?;
+        self._record_purchase(token_id, payment, forwarded);
         Ok(token_id)
     }
-}
 
-#[public]
-impl IErc721 for DEMONFT {
-    type Error = erc721::Error;
+    /// Returns the Dutch auction's start price, end price, start time and duration.
+    pub fn dutch_auction(&self) -> (U256, U256, U256, U256) {
+        (
+            self.auction_start_price.get(),
+            self.auction_end_price.get(),
+            self.auction_start.get(),
+            self.auction_duration.get(),
+        )
+    }
 
-    #[selector(name = "balanceOf")]
-    fn balance_of(&self, owner: Address) -> Result<U256, Self::Error> {
-        Ok(self.erc721.balance_of(owner)?)
+    /// Starts a Dutch auction at `start`, its price falling linearly from `start_price`
+    /// to `end_price` over `duration` seconds. A zero duration ends the auction.
+    pub fn set_dutch_auction(
+        &mut self,
+        start_price: U256,
+        end_price: U256,
+        start: U256,
+        duration: U256,
+    ) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if start_price < end_price {
+            return Err(b"Invalid auction bounds".to_vec());
+        }
+        self.auction_start_price.set(start_price);
+        self.auction_end_price.set(end_price);
+        self.auction_start.set(start);
+        self.auction_duration.set(duration);
+        Ok(())
     }
 
-    #[selector(name = "ownerOf")]
-    fn owner_of(&self, token_id: U256) -> Result<Address, Self::Error> {
-        Ok(self.erc721.owner_of(token_id)?)
+    /// Returns the price of a `dutch_auction_mint` right now. It never drops below the
+    /// end price.
+    pub fn current_price(&self) -> U256 {
+        let (start_price, end_price) = (self.auction_start_price.get(), self.auction_end_price.get());
+        let duration = self.auction_duration.get();
+        let elapsed = U256::from(self.vm().block_timestamp()).saturating_sub(self.auction_start.get());
+        if duration.is_zero() || elapsed >= duration {
+            return end_price;
+        }
+        let drop = start_price.saturating_sub(end_price) * elapsed / duration;
+        start_price.saturating_sub(drop).max(end_price)
     }
 
-    #[selector(name = "safeTransferFrom")]
-    fn safe_transfer_from_with_data(&mut self, from: Address, to: Address, token_id: U256, data: Bytes) -> Result<(), Self::Error> {
-        Ok(self.erc721.safe_transfer_from_with_data(from, to, token_id, data)?)
+    /// Mints a token to the caller at the auction's `current_price`.
+    #[payable]
+    pub fn dutch_auction_mint(&mut self, uri: String) -> Result<U256, Vec<u8>> {
+        if self.auction_duration.get().is_zero() {
+            return Err(b"No auction".to_vec());
+        }
+        if U256::from(self.vm().block_timestamp()) < self.auction_start.get() {
+            return Err(b"Auction not started".to_vec());
+        }
+        let payment = self._take_payment(self.current_price())?;
+        let forwarded = self.forward_on_mint.get() && !payment.is_zero();
+        if forwarded {
+            self.vm().transfer_eth(self.treasury.get(), payment)?;
+        }
+        let sender = self.vm().msg_sender();
+        let token_id = self._mint_one(sender, &uri)?;
+        self._record_purchase(token_id, payment, forwarded);
+        Ok(token_id)
     }
 
-    #[selector(name = "safeTransferFrom")]
-    fn safe_transfer_from(&mut self, from: Address, to: Address, token_id: U256) -> Result<(), Self::Error> {
-        Ok(self.erc721.safe_transfer_from(from, to, token_id)?)
+    /// Returns how many seconds after a paid mint its buyer can still `refund` it. Zero
+    /// means refunds are off.
+    pub fn refund_window(&self) -> U256 {
+        self.refund_window.get()
     }
 
-    #[selector(name = "transferFrom")]
-    fn transfer_from(&mut self, from: Address, to: Address, token_id: U256) -> Result<(), Self::Error> {
-        Ok(self.erc721.transfer_from(from, to, token_id)?)
+    /// Sets how many seconds after a paid mint its buyer can still `refund` it.
+    pub fn set_refund_window(&mut self, seconds: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.refund_window.set(seconds);
+        Ok(())
     }
 
-    #[selector(name = "approve")]
-    fn approve(&mut self, to: Address, token_id: U256) -> Result<(), Self::Error> {
-        Ok(self.erc721.approve(to, token_id)?)
+    /// Burns a token bought through a paid mint and pays back what the contract kept of
+    /// the payment, i.e. minus the platform fee. Only the buyer can do this, while still
+    /// holding the token and within the refund window, and not once the payment was
+    /// forwarded to the treasury.
+    pub fn refund(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        self._when_not_paused()?;
+        let window = self.refund_window.get();
+        if window.is_zero() {
+            return Err(b"Refunds disabled".to_vec());
+        }
+        let sender = self.vm().msg_sender();
+        let purchase = self.purchases.get(token_id);
+        if purchase.buyer.get() != sender || self._owner_of(token_id) != sender {
+            return Err(b"Not minter".to_vec());
+        }
+        if U256::from(self.vm().block_timestamp()) > purchase.minted_at.get() + window {
+            return Err(b"Refund window closed".to_vec());
+        }
+
+        if purchase.forwarded.get() {
+            return Err(b"Payment forwarded".to_vec());
+        }
+        let paid = purchase.paid.get();
+        // Never pay refunds out of royalties waiting to be claimed.
+        if self._free_balance() < paid {
+            return Err(b"Insufficient balance".to_vec());
+        }
+        let mut purchase = self.purchases.setter(token_id);
+        purchase.buyer.erase();
+        purchase.paid.erase();
+        purchase.minted_at.erase();
+        purchase.forwarded.erase();
+        self._burn(token_id, sender)?;
+        let spent = self.spent_by.get(sender);
+        self.spent_by.setter(sender).set(spent.saturating_sub(paid));
+        if !paid.is_zero() {
+            self.vm().transfer_eth(sender, paid)?;
+        }
+        Ok(())
     }
 
-    #[selector(name = "setApprovalForAll")]
-    fn set_approval_for_all(&mut self, operator: Address, approved: bool) -> Result<(), Self::Error> {
-        Ok(self.erc721.set_approval_for_all(operator, approved)?)
+    /// Returns the ETH `who` has paid into paid mints so far.
+    pub fn spent_by(&self, who: Address) -> U256 {
+        self.spent_by.get(who)
     }
 
-    #[selector(name = "getApproved")]
-    fn get_approved(&self, token_id: U256) -> Result<Address, Self::Error> {
-        Ok(self.erc721.get_approved(token_id)?)
+    /// Returns the most ETH a single wallet may pay into paid mints. Zero means no limit.
+    pub fn max_spend_per_wallet(&self) -> U256 {
+        self.max_spend_per_wallet.get()
     }
 
-    #[selector(name = "isApprovedForAll")]
-    fn is_approved_for_all(&self, owner: Address, operator: Address) -> bool {
-        self.erc721.is_approved_for_all(owner, operator)
+    /// Sets the most ETH a single wallet may pay into paid mints. Zero removes the limit.
+    pub fn set_max_spend_per_wallet(&mut self, cap: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.max_spend_per_wallet.set(cap);
+        Ok(())
     }
-}
 
-#[public]
-impl IErc721Metadata for DEMONFT {
-    type Error = erc721::Error;
+    /// Returns where mint proceeds go and whether `public_mint` forwards them right away
+    /// instead of keeping them in the contract for `withdraw`.
+    pub fn treasury(&self) -> (Address, bool) {
+        (self.treasury.get(), self.forward_on_mint.get())
+    }
 
-    fn name(&self) -> String {
-        self.name.get_string()
+    /// Sets the treasury and whether `public_mint` payments are forwarded to it as they
+    /// come in. It can't be cleared while either that or a transfer fee needs it.
+    pub fn set_treasury(&mut self, treasury: Address, forward_on_mint: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if (forward_on_mint || !self.transfer_fee.get().is_zero()) && treasury.is_zero() {
+            return Err(b"Zero address".to_vec());
+        }
+        self.treasury.set(treasury);
+        self.forward_on_mint.set(forward_on_mint);
+        Ok(())
     }
 
-    fn symbol(&self) -> String {
-        self.symbol.get_string()
+    /// Returns true if `who` may use `allowlist_mint`.
+    pub fn is_allowlisted(&self, who: Address) -> bool {
+        self.allowlist.get(who)
     }
 
-    fn token_uri(&self, token_id: U256) -> Result<String, Self::Error> {
-        // Check if token exists
-        let _ = self.erc721.owner_of(token_id)?;
-        let full = self.token_uris_str.get_string();
-        let bytes = full.as_bytes();
-        let mut pos = 0;
-        let mut current_token = U256::ZERO;
-        while let Some(offset) = bytes[pos..].iter().position(|&b| b == b'\n') {
-            let start = pos;
-            pos += offset;
-            if current_token == token_id {
-                let uri_bytes = &bytes[start..pos];
-                return Ok(String::from_utf8_lossy(uri_bytes).into_owned());
+    /// Adds `who` to the allowlist or removes them from it.
+    pub fn set_allowlisted(&mut self, who: Address, allowed: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.allowlist.setter(who).set(allowed);
+        Ok(())
+    }
+
+    /// Returns the allowlist discount off the public price, in basis points.
+    pub fn allowlist_discount_bps(&self) -> U256 {
+        self.allowlist_discount_bps.get()
+    }
+
+    /// Sets the allowlist discount off the public price, in basis points. 10000 makes
+    /// allowlist mints free.
+    pub fn set_allowlist_discount_bps(&mut self, bps: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if bps > U256::from(10_000) {
+            return Err(b"Discount too high".to_vec());
+        }
+        self.allowlist_discount_bps.set(bps);
+        Ok(())
+    }
+
+    /// Returns the price of an `allowlist_mint`: the `public_mint_price` minus the
+    /// discount, so it follows USD pricing too.
+    pub fn allowlist_price(&self) -> Result<U256, Vec<u8>> {
+        let discount = self.allowlist_discount_bps.get();
+        Ok(self.public_mint_price()? * (U256::from(10_000) - discount) / U256::from(10_000))
+    }
+
+    /// Mints a token to an allowlisted caller for `allowlist_price`.
+    #[payable]
+    pub fn allowlist_mint(&mut self, uri: String) -> Result<U256, Vec<u8>> {
+        let sender = self.vm().msg_sender();
+        if !self.allowlist.get(sender) {
+            return Err(b"Not allowlisted".to_vec());
+        }
+        let payment = self._take_payment(self.allowlist_price()?)?;
+        let token_id = self._mint_one(sender, &uri)?;
+        self._record_purchase(token_id, payment, false);
+        self._count_allowlist_claims(sender, U256::from(1));
+        Ok(token_id)
+    }
+
+    /// Returns a tier's Merkle root, price per token and per-wallet cap.
+    pub fn tier(&self, tier_id: U256) -> (FixedBytes<32>, U256, U256) {
+        let tier = self.tiers.get(tier_id);
+        (tier.root.get(), tier.price.get(), tier.cap.get())
+    }
+
+    /// Sets up an allowlist tier. Its Merkle tree's leaves are `keccak256(minter)` and
+    /// pairs are hashed in sorted order. A zero `root` closes the tier and a zero `cap`
+    /// means no per-wallet limit.
+    pub fn set_tier(&mut self, tier_id: U256, root: FixedBytes<32>, price: U256, cap: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        let mut tier = self.tiers.setter(tier_id);
+        tier.root.set(root);
+        tier.price.set(price);
+        tier.cap.set(cap);
+        Ok(())
+    }
+
+    /// Returns how many tokens `minter` minted through a tier.
+    pub fn tier_minted(&self, tier_id: U256, minter: Address) -> U256 {
+        self.tiers.get(tier_id).minted.get(minter)
+    }
+
+    /// Returns how many more tokens `minter` can mint through a tier, given their
+    /// Merkle `proof`. Zero if the proof doesn't verify, `U256::MAX` if the tier has no cap.
+    pub fn tier_remaining(&self, tier_id: U256, minter: Address, proof: Vec<FixedBytes<32>>) -> U256 {
+        let tier = self.tiers.get(tier_id);
+        let root = tier.root.get();
+        if root.is_zero() || !verify_merkle_proof(&proof, root, keccak(minter)) {
+            return U256::ZERO;
+        }
+        let cap = tier.cap.get();
+        if cap.is_zero() {
+            return U256::MAX;
+        }
+        cap.saturating_sub(tier.minted.get(minter))
+    }
+
+    /// Mints `quantity` tokens to the caller at a tier's price, with a `proof` that the
+    /// caller is in the tier's Merkle tree. Returns the first minted id.
+    #[payable]
+    pub fn allowlist_mint_tier(&mut self, tier_id: U256, proof: Vec<FixedBytes<32>>, quantity: U256) -> Result<U256, Vec<u8>> {
+        let sender = self.vm().msg_sender();
+        let tier = self.tiers.get(tier_id);
+        let root = tier.root.get();
+        if root.is_zero() {
+            return Err(b"Unknown tier".to_vec());
+        }
+        if !verify_merkle_proof(&proof, root, keccak(sender)) {
+            return Err(b"Invalid proof".to_vec());
+        }
+        if quantity.is_zero() {
+            return Err(b"Zero quantity".to_vec());
+        }
+        self._check_batch_size(quantity.try_into().unwrap_or(usize::MAX))?;
+        let minted = tier.minted.get(sender) + quantity;
+        let cap = tier.cap.get();
+        if !cap.is_zero() && minted > cap {
+            return Err(b"Tier cap reached".to_vec());
+        }
+        let price = tier.price.get();
+        let total = price.checked_mul(quantity).ok_or(b"Quantity too large".to_vec())?;
+        let count: u64 = quantity.try_into().map_err(|_| b"Quantity too large".to_vec())?;
+        self.tiers.setter(tier_id).minted.setter(sender).set(minted);
+
+        // Like `public_mint`, each token records its share of what the contract kept,
+        // the first one taking any remainder.
+        let payment = self._take_payment(total)?;
+        let share = payment / quantity;
+        self._before_mint(sender, quantity)?;
+        let mut first_id = U256::ZERO;
+        for i in 0..count {
+            let token_id = self._mint_next(sender, "")?;
+            if i == 0 {
+                first_id = token_id;
+                self._record_purchase(token_id, share + payment % quantity, false);
+            } else {
+                self._record_purchase(token_id, share, false);
             }
-            current_token += U256::from(1);
-            pos += 1; // skip \n
         }
-        // Last one without \n
-        if current_token == token_id && pos < bytes.len() {
-            let uri_bytes = &bytes[pos..];
-            Ok(String::from_utf8_lossy(uri_bytes).into_owned())
-        } else {
-            Ok(String::new())
+        self._count_allowlist_claims(sender, quantity);
+        Ok(first_id)
+    }
+
+    /// Mints one token per URI to `to` as a single sequential run. Only the first id
+    /// of the run (and the first id after any individually sold id it skips) gets its
+    /// owner written, so the cost barely grows with the batch size.
+    /// Returns the first minted token id.
+    pub fn batch_mint(&mut self, to: Address, uris: Vec<String>) -> Result<U256, Vec<u8>> {
+        if to.is_zero() {
+            return Err(erc721::Error::from(ERC721InvalidReceiver { receiver: to }).into());
+        }
+        if uris.is_empty() {
+            return Err(b"Empty batch".to_vec());
         }
+        self._check_batch_size(uris.len())?;
+        let quantity = U256::from(uris.len());
+        self._before_mint(to, quantity)?;
+
+        let first_id = self._next_sequential_id(self.next_id.get());
+        self._snapshot_balance(to);
+        self._move_legacy_balance(to);
+        let balance = self.balances.get(to);
+        self.balances.setter(to).set(balance + quantity);
+
+        let now = U256::from(self.vm().block_timestamp());
+        let mut token_id = first_id;
+        for uri in &uris {
+            let id = self._next_sequential_id(token_id);
+            if id != token_id || id == first_id || self.run_breaks.get(id) {
+                // Runs can't span a skipped id, so start a new one after it.
+                self.owners.setter(id).set(to);
+            }
+            self.acquired_at.setter(id).set(now);
+            self.minted_at.setter(id).set(now);
+            self._assign_seed(id, now);
+            self._add_owned(to, id);
+            log(self.vm(), Transfer { from: Address::ZERO, to, token_id: id });
+            self._set_minted_uri(id, uri)?;
+            self._auto_approve(to, id)?;
+            token_id = id + U256::from(1);
+        }
+
+        self.next_id.set(token_id);
+        self._after_mint(to, token_id - U256::from(1), quantity);
+        Ok(first_id)
     }
-}
 
-#[public]
-impl IErc165 for DEMONFT {
+    /// Returns the largest batch accepted by batch functions. Zero means no limit.
+    pub fn max_batch_size(&self) -> U256 {
+        self.max_batch_size.get()
+    }
+
+    /// Sets the largest batch accepted by batch functions. Zero removes the limit.
+    pub fn set_max_batch_size(&mut self, max_batch_size: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.max_batch_size.set(max_batch_size);
+        Ok(())
+    }
+
+    /// Returns the longest token URI in bytes that minting and `set_token_uri` accept.
+    /// Zero means no limit.
+    pub fn max_uri_length(&self) -> U256 {
+        self.max_uri_length.get()
+    }
+
+    /// Sets the longest token URI in bytes that minting and `set_token_uri` accept. Zero
+    /// removes the limit.
+    pub fn set_max_uri_length(&mut self, max_uri_length: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.max_uri_length.set(max_uri_length);
+        Ok(())
+    }
+
+    /// Returns true if `mint` and `batch_mint` may mint to contracts.
+    pub fn allow_contract_recipients(&self) -> bool {
+        !self.block_contract_recipients.get()
+    }
+
+    /// Allows or blocks minting to contracts through `mint` and `batch_mint`, which
+    /// don't check that the recipient can handle ERC-721 tokens.
+    pub fn set_allow_contract_recipients(&mut self, allowed: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.block_contract_recipients.set(!allowed);
+        Ok(())
+    }
+
+    /// Returns true while tokens can only be minted or transferred to allowed receivers.
+    pub fn receiver_allowlist_enabled(&self) -> bool {
+        self.receiver_allowlist_enabled.get()
+    }
+
+    /// Restricts mints and transfers to allowed receivers (`enabled`), or lifts that.
+    pub fn set_receiver_allowlist_enabled(&mut self, enabled: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.receiver_allowlist_enabled.set(enabled);
+        Ok(())
+    }
+
+    /// Returns true if `who` may receive tokens while the receiver allowlist is on. The
+    /// owner always may.
+    pub fn is_allowed_receiver(&self, who: Address) -> bool {
+        who == self.owner.get() || self.allowed_receivers.get(who)
+    }
+
+    /// Adds `who` to the receiver allowlist (`allowed`) or removes them from it.
+    pub fn set_allowed_receiver(&mut self, who: Address, allowed: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.allowed_receivers.setter(who).set(allowed);
+        Ok(())
+    }
+
+    /// Returns how many copies edition `id` can have and how many were minted.
+    pub fn edition_supply(&self, id: U256) -> (U256, U256) {
+        (self.edition_supply.get(id), self.edition_minted.get(id))
+    }
+
+    /// Sets how many copies edition `id` can have, never below the copies already minted.
+    pub fn set_edition_cap(&mut self, id: U256, cap: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if id.is_zero() {
+            return Err(b"Invalid edition".to_vec());
+        }
+        if cap < self.edition_minted.get(id) {
+            return Err(b"Cap below minted".to_vec());
+        }
+        self.edition_supply.setter(id).set(cap);
+        Ok(())
+    }
+
+    /// Mints a new token to `to` as the next copy of edition `id`. Returns the token id.
+    pub fn mint_edition(&mut self, id: U256, to: Address) -> Result<U256, Vec<u8>> {
+        self._only_owner()?;
+        let minted = self.edition_minted.get(id);
+        if minted >= self.edition_supply.get(id) {
+            return Err(b"Edition sold out".to_vec());
+        }
+        self.edition_minted.setter(id).set(minted + U256::from(1));
+        let token_id = self._mint_one(to, "")?;
+        self.token_edition.setter(token_id).set(id);
+        Ok(token_id)
+    }
+
+    /// Returns the edition `token_id` is a copy of, zero when it isn't part of one.
+    pub fn edition_of(&self, token_id: U256) -> U256 {
+        self.token_edition.get(token_id)
+    }
+
+    /// Returns true if mints reject a URI that an earlier mint already used.
+    pub fn enforce_unique_uri(&self) -> bool {
+        self.enforce_unique_uri.get()
+    }
+
+    /// Turns duplicate URI rejection on or off. Only URIs minted while it is on are
+    /// remembered.
+    pub fn set_enforce_unique_uri(&mut self, enabled: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.enforce_unique_uri.set(enabled);
+        Ok(())
+    }
+
+    /// Returns the operator newly minted tokens get approved for, e.g. a staking
+    /// contract, and whether that is switched on.
+    pub fn auto_approve_operator(&self) -> (Address, bool) {
+        (self.auto_approve_operator.get(), self.auto_approve.get())
+    }
+
+    /// Sets the operator every newly minted token is approved for, so holders can stake
+    /// without approving first, and switches that on or off.
+    pub fn set_auto_approve_operator(&mut self, operator: Address, enabled: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if enabled && operator.is_zero() {
+            return Err(b"Zero address".to_vec());
+        }
+        self.auto_approve_operator.set(operator);
+        self.auto_approve.set(enabled);
+        Ok(())
+    }
+
+    /// Returns the sale price of an unminted token, zero when it isn't for sale.
+    pub fn token_price(&self, token_id: U256) -> U256 {
+        self.token_price.get(token_id)
+    }
+
+    /// Lists an unminted token for sale at `price`. A zero price takes it off sale.
+    /// Sequential mints skip listed ids.
+    pub fn set_token_price(&mut self, token_id: U256, price: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if !self._owner_of(token_id).is_zero() {
+            return Err(b"Already minted".to_vec());
+        }
+        if !price.is_zero() {
+            // A skipped listed id belongs to no run, like the ids `set_next_id` skips.
+            self.run_breaks.setter(token_id).set(true);
+        }
+        self.token_price.setter(token_id).set(price);
+        Ok(())
+    }
+
+    /// Buys a token listed with `set_token_price`, minting that exact id to the caller.
+    /// Any payment above the price stays in the contract.
+    #[payable]
+    pub fn buy(&mut self, token_id: U256, uri: String) -> Result<(), Vec<u8>> {
+        let price = self.token_price.get(token_id);
+        if price.is_zero() {
+            return Err(b"Not for sale".to_vec());
+        }
+        if !self._owner_of(token_id).is_zero() {
+            return Err(b"Already sold".to_vec());
+        }
+        let payment = self._take_payment(price)?;
+
+        let buyer = self.vm().msg_sender();
+        self._mint_explicit(buyer, token_id, &uri)?;
+        self._record_purchase(token_id, payment, false);
+        Ok(())
+    }
+
+    /// Mints to `to` the token whose id is `keccak256(salt)`, so the same salt gives
+    /// the same id on every deployment. Returns the id.
+    pub fn mint_deterministic(&mut self, to: Address, salt: FixedBytes<32>, uri: String) -> Result<U256, Vec<u8>> {
+        let token_id = U256::from_be_bytes(keccak(salt).0);
+        self._mint_explicit(to, token_id, &uri)?;
+        Ok(token_id)
+    }
+
+    /// Returns `keccak256(abi.encode(address(this), token_id))`, an id for the token
+    /// that no token of another collection shares.
+    pub fn token_global_id(&self, token_id: U256) -> FixedBytes<32> {
+        keccak((self.vm().contract_address(), token_id).abi_encode())
+    }
+
+    /// Returns every interface id `supports_interface` returns true for.
+    pub fn declared_interfaces(&self) -> Vec<FixedBytes<4>> {
+        DECLARED_INTERFACES.iter().copied().map(FixedBytes).collect()
+    }
+
+    /// Returns the owner of each token in `token_ids`, with `Address::ZERO` for tokens
+    /// that don't exist. Takes at most `max_batch_size` ids.
+    pub fn owners_of(&self, token_ids: Vec<U256>) -> Result<Vec<Address>, Vec<u8>> {
+        self._check_batch_size(token_ids.len())?;
+        Ok(token_ids.into_iter().map(|id| self._owner_of(id)).collect())
+    }
+
+    /// Returns for each token in `token_ids` whether it was ever minted, and its owner.
+    /// Burned tokens come back as `(true, Address::ZERO)`, so they can be told apart
+    /// from ids that were never minted. Takes at most `max_batch_size` ids.
+    pub fn existence_and_owners(&self, token_ids: Vec<U256>) -> Result<Vec<(bool, Address)>, Vec<u8>> {
+        self._check_batch_size(token_ids.len())?;
+        Ok(token_ids
+            .into_iter()
+            .map(|id| {
+                let owner = self._owner_of(id);
+                (!owner.is_zero() || self.burned.get(id), owner)
+            })
+            .collect())
+    }
+
+    /// Returns for each of `token_ids` whether it currently exists and its `token_uri`,
+    /// `(false, "")` for tokens that don't. Takes at most `max_batch_size` ids.
+    pub fn uris_of(&self, token_ids: Vec<U256>) -> Result<Vec<(bool, String)>, Vec<u8>> {
+        self._check_batch_size(token_ids.len())?;
+        Ok(token_ids
+            .into_iter()
+            .map(|id| match IErc721Metadata::token_uri(self, id) {
+                Ok(uri) => (true, uri),
+                Err(_) => (false, String::new()),
+            })
+            .collect())
+    }
+
+    /// Returns whether `operator` is approved for all of `owner`'s tokens, and the
+    /// approved address of each token in `token_ids`, `Address::ZERO` for tokens that
+    /// don't exist. Takes at most `max_batch_size` ids.
+    pub fn approvals_of(&self, owner: Address, operator: Address, token_ids: Vec<U256>) -> Result<(bool, Vec<Address>), Vec<u8>> {
+        self._check_batch_size(token_ids.len())?;
+        let approved = token_ids
+            .into_iter()
+            .map(|id| if self._owner_of(id).is_zero() { Address::ZERO } else { self.erc721._get_approved(id) })
+            .collect();
+        Ok((self.erc721.is_approved_for_all(owner, operator), approved))
+    }
+
+    /// Returns true if `spender` may transfer `token_id`: its owner, its approved
+    /// address, or an operator approved by the owner.
+    pub fn can_transfer(&self, spender: Address, token_id: U256) -> bool {
+        let owner = self._owner_of(token_id);
+        !owner.is_zero() && self.erc721._is_authorized(owner, spender, token_id)
+    }
+
+    /// Burns `token_id`. The caller must own it or be approved for it.
+    pub fn burn(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        self._when_not_paused()?;
+        let sender = self.vm().msg_sender();
+        self._burn(token_id, sender)
+    }
+
+    /// Burns every token in `token_ids`. The caller must own or be approved for each of
+    /// them, otherwise nothing is burned.
+    pub fn batch_burn(&mut self, token_ids: Vec<U256>) -> Result<(), Vec<u8>> {
+        self._when_not_paused()?;
+        self._check_batch_size(token_ids.len())?;
+        let sender = self.vm().msg_sender();
+        for token_id in token_ids {
+            self._burn(token_id, sender)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the ETH paid out by `burn_for_refund`.
+    pub fn buyback_amount(&self) -> U256 {
+        self.buyback_amount.get()
+    }
+
+    /// Sets the ETH paid out by `burn_for_refund`. Zero turns buybacks off.
+    pub fn set_buyback_amount(&mut self, amount: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.buyback_amount.set(amount);
+        Ok(())
+    }
+
+    /// Burns `token_id` and pays `buyback_amount` to the caller, who must own the
+    /// token or be approved for it.
+    pub fn burn_for_refund(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        self._when_not_paused()?;
+        let amount = self.buyback_amount.get();
+        if amount.is_zero() {
+            return Err(b"No buyback".to_vec());
+        }
+        if self._free_balance() < amount {
+            return Err(b"Insufficient balance".to_vec());
+        }
+
+        let sender = self.vm().msg_sender();
+        self._burn(token_id, sender)?;
+        self.vm().transfer_eth(sender, amount)?;
+        Ok(())
+    }
+
+    /// Returns how many tokens were burned, through any burn path.
+    pub fn total_burned(&self) -> U256 {
+        self.burned_count.get()
+    }
+
+    /// Returns true if `who` ever received a newly minted token.
+    pub fn has_ever_minted(&self, who: Address) -> bool {
+        self.has_minted.get(who)
+    }
+
+    /// Returns the contract rendering token URIs, or `Address::ZERO` when stored URIs are used.
+    pub fn metadata_renderer(&self) -> Address {
+        self.metadata_renderer.get()
+    }
+
+    /// Delegates `token_uri` to `renderer` (zero restores stored URIs) and tells
+    /// marketplaces to re-fetch every minted token.
+    pub fn set_metadata_renderer(&mut self, renderer: Address) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.metadata_renderer.set(renderer);
+        self._refresh_all_metadata();
+        Ok(())
+    }
+
+    /// Returns the base URI of tokens without a URI of their own, and the suffix
+    /// appended after their id.
+    pub fn base_uri(&self) -> (String, String) {
+        (self.base_uri.get_string(), self.uri_suffix.get_string())
+    }
+
+    /// Sets the base URI of tokens without a URI of their own.
+    pub fn set_base_uri(&mut self, base_uri: String) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_base_uri_not_frozen()?;
+        self._check_uri_length(base_uri.as_bytes())?;
+        self.base_uri.set_str(base_uri.as_str());
+        self._refresh_all_metadata();
+        Ok(())
+    }
+
+    /// Sets the suffix appended after the token id to the base URI, e.g. `.json`.
+    pub fn set_uri_suffix(&mut self, suffix: String) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_base_uri_not_frozen()?;
+        self.uri_suffix.set_str(suffix.as_str());
+        self._refresh_all_metadata();
+        Ok(())
+    }
+
+    /// Returns true once the base URI and suffix can no longer change.
+    pub fn base_uri_frozen(&self) -> bool {
+        self.base_uri_frozen.get()
+    }
+
+    /// Locks the base URI and suffix for good. Per-token URIs stay editable.
+    pub fn freeze_base_uri(&mut self) -> Result<(), Vec<u8>> {
+        self._only_owner_without_multisig()?;
+        self.base_uri_frozen.set(true);
+        Ok(())
+    }
+
+    /// Replaces the URI of an existing token.
+    pub fn set_token_uri(&mut self, token_id: U256, uri: String) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._require_owned(token_id)?;
+        self._check_uri_length(uri.as_bytes())?;
+        self._set_token_uri(token_id, &uri);
+        log(self.vm(), MetadataUpdate { _tokenId: token_id });
+        Ok(())
+    }
+
+    /// Replaces the URI of an existing token with raw bytes, for binary metadata that
+    /// isn't valid UTF-8. `token_uri_raw` returns them unchanged.
+    pub fn set_token_uri_bytes(&mut self, token_id: U256, uri: Bytes) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._require_owned(token_id)?;
+        self._check_uri_length(&uri)?;
+        self.token_uris.setter(token_id).0.set_bytes(&*uri);
+        log(self.vm(), MetadataUpdate { _tokenId: token_id });
+        Ok(())
+    }
+
+    /// Emits `MetadataUpdate` for `token_id` so marketplaces re-fetch it, e.g. after its
+    /// off-chain metadata was fixed.
+    pub fn emit_metadata_update(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        log(self.vm(), MetadataUpdate { _tokenId: token_id });
+        Ok(())
+    }
+
+    /// Emits `BatchMetadataUpdate` for ids `from..=to` so marketplaces re-fetch them.
+    pub fn emit_batch_metadata_update(&mut self, from: U256, to: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if from > to {
+            return Err(b"Invalid range".to_vec());
+        }
+        log(self.vm(), BatchMetadataUpdate { _fromTokenId: from, _toTokenId: to });
+        Ok(())
+    }
+
+    /// Copies the legacy newline separated URIs of ids `start..start + count` into the
+    /// per-token map, so the legacy list can be migrated in chunks. Ids that already have
+    /// a URI in the map are left alone, which makes re-running a chunk harmless. Ids past
+    /// the sequentially minted range are skipped.
+    pub fn migrate_uris(&mut self, start: U256, count: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_batch_size(count.try_into().unwrap_or(usize::MAX))?;
+        let end = start.checked_add(count).ok_or(b"Invalid range".to_vec())?.min(self.next_id.get());
+
+        let full = self.token_uris_str.get_string();
+        let mut token_id = U256::ZERO;
+        for uri in full.split('\n') {
+            if token_id >= end {
+                break;
+            }
+            if token_id >= start && !uri.is_empty() && self.token_uris.get(token_id).is_empty() {
+                self._set_token_uri(token_id, uri);
+            }
+            token_id += U256::from(1);
+        }
+        Ok(())
+    }
+
+    /// Returns the HTTP gateway `token_http_uri` rewrites `ipfs://` URIs to.
+    pub fn ipfs_gateway(&self) -> String {
+        self.ipfs_gateway.get_string()
+    }
+
+    /// Sets the HTTP gateway for `token_http_uri`, e.g. `https://ipfs.io`.
+    pub fn set_ipfs_gateway(&mut self, gateway: String) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.ipfs_gateway.set_str(gateway.trim_end_matches('/'));
+        Ok(())
+    }
+
+    /// Returns the exact bytes stored as a token's URI, for binary metadata that
+    /// `token_uri` would mangle into UTF-8. Tokens without their own URI get the
+    /// fallback `token_uri` uses.
+    pub fn token_uri_raw(&self, token_id: U256) -> Result<Bytes, Vec<u8>> {
+        self._require_owned(token_id)?;
+        let raw = self.token_uris.get(token_id).0.get_bytes();
+        if !raw.is_empty() {
+            return Ok(raw.into());
+        }
+        Ok(self._stored_token_uri(token_id).into_bytes().into())
+    }
+
+    /// Returns the stored URI of a token with `ipfs://<cid>` rewritten to
+    /// `<gateway>/ipfs/<cid>`. Other URIs, or any URI while no gateway is set, come
+    /// back unchanged.
+    pub fn token_http_uri(&self, token_id: U256) -> Result<String, Vec<u8>> {
+        self._require_owned(token_id)?;
+        let uri = self._stored_token_uri(token_id);
+        let gateway = self.ipfs_gateway.get_string();
+        match uri.strip_prefix("ipfs://") {
+            Some(cid) if !gateway.is_empty() => Ok(format!("{gateway}/ipfs/{cid}")),
+            _ => Ok(uri),
+        }
+    }
+
+    /// Returns true if stored token URIs are served with a `v=<hash>` query appended.
+    pub fn append_version_query(&self) -> bool {
+        self.append_version_query.get()
+    }
+
+    /// Turns the cache-busting `v=<hash>` query on stored token URIs on or off.
+    pub fn set_append_version_query(&mut self, enabled: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.append_version_query.set(enabled);
+        self._refresh_all_metadata();
+        Ok(())
+    }
+
+    /// Returns the URI every token reports until the collection is revealed.
+    pub fn unrevealed_uri(&self) -> String {
+        self.unrevealed_uri.get_string()
+    }
+
+    /// Sets the pre-reveal URI. While it's empty, tokens are never hidden.
+    pub fn set_unrevealed_uri(&mut self, uri: String) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.unrevealed_uri.set_str(uri.as_str());
+        self._refresh_all_metadata();
+        Ok(())
+    }
+
+    /// Returns the timestamp of the scheduled reveal, zero when none is scheduled.
+    pub fn reveal_at(&self) -> U256 {
+        self.reveal_at.get()
+    }
+
+    /// Returns true once the owner revealed the collection or its scheduled reveal time passed.
+    pub fn is_revealed(&self) -> bool {
+        let reveal_at = self.reveal_at.get();
+        self.revealed.get() || (!reveal_at.is_zero() && U256::from(self.vm().block_timestamp()) >= reveal_at)
+    }
+
+    /// Reveals the collection right away, regardless of any schedule.
+    pub fn reveal(&mut self) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.revealed.set(true);
+        log(self.vm(), Revealed { base_uri: self.base_uri.get_string() });
+        self._refresh_all_metadata();
+        Ok(())
+    }
+
+    /// Reveals the collection with `real_base_uri` as its base URI and freezes the base
+    /// URI in the same transaction.
+    pub fn reveal_and_freeze(&mut self, real_base_uri: String) -> Result<(), Vec<u8>> {
+        self._only_owner_without_multisig()?;
+        self._reveal_and_freeze(real_base_uri)
+    }
+
+    /// Schedules the reveal for the future timestamp `at`, replacing any earlier schedule.
+    pub fn schedule_reveal(&mut self, at: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if self.is_revealed() {
+            return Err(b"Already revealed".to_vec());
+        }
+        if at <= U256::from(self.vm().block_timestamp()) {
+            return Err(b"Reveal time passed".to_vec());
+        }
+        self.reveal_at.set(at);
+        Ok(())
+    }
+
+    /// Cancels a scheduled reveal that hasn't triggered yet.
+    pub fn cancel_reveal(&mut self) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if self.is_revealed() {
+            return Err(b"Already revealed".to_vec());
+        }
+        self.reveal_at.set(U256::ZERO);
+        Ok(())
+    }
+
+    /// ERC-2981: returns who receives the royalty on a sale of `token_id` and how much.
+    /// This is a share of `sale_price` in bps, from the token's override if it has one,
+    /// or the flat fee while flat mode is on. The receiver is the token's holder while
+    /// `royalty_follows_owner` is on. Without a receiver the royalty goes to the owner,
+    /// unless there is no royalty at all.
+    #[selector(name = "royaltyInfo")]
+    pub fn royalty_info(&self, token_id: U256, sale_price: U256) -> (Address, U256) {
+        let royalty = self.token_royalties.get(token_id);
+        let (mut receiver, bps) = if royalty.receiver.get().is_zero() {
+            (self.royalty_receiver.get(), self.royalty_bps.get())
+        } else {
+            (royalty.receiver.get(), royalty.bps.get())
+        };
+        if self.royalty_follows_owner.get() {
+            receiver = self._owner_of(token_id);
+            if receiver.is_zero() {
+                return (Address::ZERO, U256::ZERO);
+            }
+        } else if receiver.is_zero() {
+            if bps.is_zero() && !self.flat_royalty.get() {
+                return (Address::ZERO, U256::ZERO);
+            }
+            receiver = self.owner.get();
+        }
+        let amount = if self.flat_royalty.get() {
+            self.flat_fee.get()
+        } else {
+            sale_price * bps / U256::from(10_000)
+        };
+        (receiver, amount)
+    }
+
+    /// Credits the attached ETH, a royalty for `token_id`, to the token's royalty
+    /// receiver to claim later. While the contract itself is the receiver, e.g. as
+    /// escrow for staked tokens, the token's staker or holder is credited instead.
+    #[payable]
+    pub fn pay_royalty(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        let (mut receiver, _) = self.royalty_info(token_id, U256::ZERO);
+        if receiver.is_zero() || receiver == self.vm().contract_address() {
+            let staker = self.stakes.get(token_id).staker.get();
+            receiver = if staker.is_zero() { self._owner_of(token_id) } else { staker };
+        }
+        if receiver.is_zero() {
+            return Err(b"No royalty receiver".to_vec());
+        }
+
+        let amount = self.vm().msg_value();
+        let pending = self.pending_royalties.get(receiver);
+        self.pending_royalties.setter(receiver).set(pending + amount);
+        self.total_pending_royalties.set(self.total_pending_royalties.get() + amount);
+        Ok(())
+    }
+
+    /// Returns the royalties `receiver` can claim with `claim_royalty`.
+    pub fn pending_royalty(&self, receiver: Address) -> U256 {
+        self.pending_royalties.get(receiver)
+    }
+
+    /// Sends the caller every royalty credited to them. Returns the amount.
+    pub fn claim_royalty(&mut self) -> Result<U256, Vec<u8>> {
+        let receiver = self.vm().msg_sender();
+        let amount = self.pending_royalties.get(receiver);
+        if amount.is_zero() {
+            return Err(b"Nothing to claim".to_vec());
+        }
+        self.pending_royalties.setter(receiver).set(U256::ZERO);
+        self.total_pending_royalties.set(self.total_pending_royalties.get() - amount);
+        self.vm().transfer_eth(receiver, amount)?;
+        Ok(amount)
+    }
+
+    /// Returns the total ETH tipped to the holders of `token_id`.
+    pub fn tips_received(&self, token_id: U256) -> U256 {
+        self.token_tips.get(token_id)
+    }
+
+    /// Sends the attached ETH as a tip to the current holder of `token_id`, or to its
+    /// staker while the contract holds it staked.
+    #[payable]
+    pub fn tip(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        let mut holder = self._require_owned(token_id)?;
+        if holder == self.vm().contract_address() {
+            holder = self.stakes.get(token_id).staker.get();
+            if holder.is_zero() {
+                return Err(b"No tip receiver".to_vec());
+            }
+        }
+        let amount = self.vm().msg_value();
+        let tips = self.token_tips.get(token_id);
+        self.token_tips.setter(token_id).set(tips + amount);
+        self.vm().transfer_eth(holder, amount)?;
+        Ok(())
+    }
+
+    /// Returns the default royalty in basis points, used by tokens without an override.
+    pub fn default_royalty_bps(&self) -> U256 {
+        self.royalty_bps.get()
+    }
+
+    /// Returns how many tokens have a royalty override.
+    pub fn custom_royalty_count(&self) -> U256 {
+        self.custom_royalty_count.get()
+    }
+
+    /// Overrides the royalty receiver and share of a single token.
+    pub fn set_token_royalty(&mut self, token_id: U256, receiver: Address, bps: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_royalties_not_frozen()?;
+        self._set_token_royalty(token_id, receiver, bps)
+    }
+
+    /// Overrides the royalties of several tokens at once, `token_ids[i]` getting
+    /// `receivers[i]` and `bps[i]`.
+    pub fn batch_set_token_royalty(
+        &mut self,
+        token_ids: Vec<U256>,
+        receivers: Vec<Address>,
+        bps: Vec<U256>,
+    ) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_royalties_not_frozen()?;
+        if token_ids.len() != receivers.len() || token_ids.len() != bps.len() {
+            return Err(b"Length mismatch".to_vec());
+        }
+        self._check_batch_size(token_ids.len())?;
+        for ((token_id, receiver), bps) in token_ids.into_iter().zip(receivers).zip(bps) {
+            self._set_token_royalty(token_id, receiver, bps)?;
+        }
+        Ok(())
+    }
+
+    /// Removes a token's royalty override so it falls back to the default.
+    pub fn reset_token_royalty(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_royalties_not_frozen()?;
+        if self.token_royalties.get(token_id).receiver.get().is_zero() {
+            return Ok(());
+        }
+        self.custom_royalty_count.set(self.custom_royalty_count.get() - U256::from(1));
+        let mut royalty = self.token_royalties.setter(token_id);
+        royalty.receiver.set(Address::ZERO);
+        royalty.bps.set(U256::ZERO);
+        log(self.vm(), TokenRoyaltyReset { tokenId: token_id });
+        Ok(())
+    }
+
+    /// Sets the royalty receiver and its share of each sale in basis points.
+    pub fn set_royalty(&mut self, receiver: Address, bps: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_royalties_not_frozen()?;
+        if bps > U256::from(10_000) {
+            return Err(b"Royalty too high".to_vec());
+        }
+        self.royalty_receiver.set(receiver);
+        self.royalty_bps.set(bps);
+        log(self.vm(), DefaultRoyaltySet { receiver, bps });
+        Ok(())
+    }
+
+    /// Returns whether the flat royalty mode is on and the fee it charges per sale.
+    pub fn flat_royalty(&self) -> (bool, U256) {
+        (self.flat_royalty.get(), self.flat_fee.get())
+    }
+
+    /// Switches between a flat `fee` per sale (`enabled`) and the bps royalty.
+    pub fn set_flat_royalty(&mut self, enabled: bool, fee: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_royalties_not_frozen()?;
+        self.flat_royalty.set(enabled);
+        self.flat_fee.set(fee);
+        Ok(())
+    }
+
+    /// Returns true if each token's royalties go to whoever holds it.
+    pub fn royalty_follows_owner(&self) -> bool {
+        self.royalty_follows_owner.get()
+    }
+
+    /// Sends each token's royalties to whoever holds it (`enabled`) or to the configured
+    /// receivers.
+    pub fn set_royalty_follows_owner(&mut self, enabled: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._check_royalties_not_frozen()?;
+        self.royalty_follows_owner.set(enabled);
+        Ok(())
+    }
+
+    /// Returns true once royalties can no longer change.
+    pub fn are_royalties_frozen(&self) -> bool {
+        self.royalties_frozen.get()
+    }
+
+    /// Locks every royalty setting, default and per token, for good.
+    pub fn freeze_royalties(&mut self) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.royalties_frozen.set(true);
+        Ok(())
+    }
+
+    /// Returns how token URIs are produced, one of the `METADATA_MODE_*` values.
+    pub fn metadata_mode(&self) -> u8 {
+        self.metadata_mode.get().to::<u8>()
+    }
+
+    /// Switches between stored URIs and on-chain JSON or raw SVG data URIs.
+    pub fn set_metadata_mode(&mut self, mode: u8) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if mode > METADATA_MODE_SVG {
+            return Err(b"Invalid mode".to_vec());
+        }
+        self.metadata_mode.set(stylus_sdk::alloy_primitives::U8::from(mode));
+        self._refresh_all_metadata();
+        Ok(())
+    }
+
+    /// Returns the partner collection whose tokens can be burned to claim one here.
+    pub fn redemption_collection(&self) -> Address {
+        self.redemption_collection.get()
+    }
+
+    /// Sets the partner collection used by `claim_by_burning`. Zero disables claims.
+    pub fn set_redemption_collection(&mut self, collection: Address) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.redemption_collection.set(collection);
+        Ok(())
+    }
+
+    /// Burns the caller's partner token and mints a token from this collection to them.
+    /// The caller must have approved this contract on the partner collection.
+    pub fn claim_by_burning(&mut self, partner_token_id: U256, uri: String) -> Result<U256, Vec<u8>> {
+        let collection = self.redemption_collection.get();
+        if collection.is_zero() {
+            return Err(b"Redemption disabled".to_vec());
+        }
+
+        let caller = self.vm().msg_sender();
+        let partner = IRedemptionCollection::new(collection);
+        if partner.owner_of(&*self, partner_token_id)? != caller {
+            return Err(b"Not partner token holder".to_vec());
+        }
+        partner.transfer_from(&mut *self, caller, BURN_ADDRESS, partner_token_id)?;
+
+        self._mint_one(caller, &uri)
+    }
+
+    /// Sends the contract's full ETH balance to the owner.
+    pub fn withdraw(&mut self) -> Result<(), Vec<u8>> {
+        let owner = self.owner.get();
+        self.withdraw_to(owner)
+    }
+
+    /// Returns the ETH the contract currently holds.
+    pub fn contract_balance(&self) -> U256 {
+        self.vm().balance(self.vm().contract_address())
+    }
+
+    /// Sends the contract's ETH balance to `to`, minus royalties waiting to be claimed.
+    pub fn withdraw_to(&mut self, to: Address) -> Result<(), Vec<u8>> {
+        self._only_owner_without_multisig()?;
+        self._withdraw_to(to)
+    }
+
+    /// Sends exactly `amount` of the contract's ETH to `to`, leaving the rest in place.
+    /// Royalties waiting to be claimed can't be withdrawn.
+    pub fn withdraw_amount(&mut self, to: Address, amount: U256) -> Result<(), Vec<u8>> {
+        self._only_owner_without_multisig()?;
+        if to.is_zero() {
+            return Err(b"Zero address".to_vec());
+        }
+        if self._free_balance() < amount {
+            return Err(b"Insufficient balance".to_vec());
+        }
+        self.vm().transfer_eth(to, amount)?;
+        log(self.vm(), EthWithdrawn { to, amount });
+        Ok(())
+    }
+
+    /// Sends the contract's full balance of an ERC-20 `token` to `to`.
+    pub fn withdraw_erc20(&mut self, token: Address, to: Address) -> Result<(), Vec<u8>> {
+        self._only_owner_without_multisig()?;
+        self._withdraw_erc20(token, to)
+    }
+
+    /// Returns true while `token_id` is locked by `lock_for` and can't be transferred.
+    pub fn is_locked(&self, token_id: U256) -> bool {
+        U256::from(self.vm().block_timestamp()) < self.lock_until.get(token_id)
+    }
+
+    /// Returns when the lock on `token_id` runs out, zero if it was never locked.
+    pub fn lock_until(&self, token_id: U256) -> U256 {
+        self.lock_until.get(token_id)
+    }
+
+    /// Locks one of the caller's tokens against transfers for `seconds`. The lock runs
+    /// out on its own and can't be lifted or shortened before then.
+    pub fn lock_for(&mut self, token_id: U256, seconds: U256) -> Result<(), Vec<u8>> {
+        if self._require_owned(token_id)? != self.vm().msg_sender() {
+            return Err(b"Not token owner".to_vec());
+        }
+        if self.is_locked(token_id) {
+            return Err(b"Token locked".to_vec());
+        }
+        let until = U256::from(self.vm().block_timestamp()) + seconds;
+        self.lock_until.setter(token_id).set(until);
+        Ok(())
+    }
+
+    /// Returns how long a token must be held after being minted or received before it
+    /// can be transferred. Zero means no hold period.
+    pub fn min_hold_seconds(&self) -> U256 {
+        self.min_hold_seconds.get()
+    }
+
+    /// Sets how long a token must be held before it can be transferred again.
+    pub fn set_min_hold_seconds(&mut self, seconds: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.min_hold_seconds.set(seconds);
+        Ok(())
+    }
+
+    /// Returns when `token_id` last changed hands, or was minted.
+    pub fn acquired_at(&self, token_id: U256) -> U256 {
+        self.acquired_at.get(token_id)
+    }
+
+    /// Returns when `token_id` was minted.
+    pub fn minted_at(&self, token_id: U256) -> Result<U256, Vec<u8>> {
+        self._require_owned(token_id)?;
+        Ok(self.minted_at.get(token_id))
+    }
+
+    /// Returns the seed `token_id` got at mint, for renderers deriving its traits. The
+    /// seed is only pseudo-random: whoever mints can predict it, and a sequencer can
+    /// influence it, so it mustn't decide anything of value.
+    pub fn token_seed(&self, token_id: U256) -> Result<FixedBytes<32>, Vec<u8>> {
+        self._require_owned(token_id)?;
+        Ok(self.token_seed.get(token_id))
+    }
+
+    /// Returns the ETH every `transferFrom` and `safeTransferFrom` must pay, which goes
+    /// to the treasury. Zero means transfers are free.
+    pub fn transfer_fee(&self) -> U256 {
+        self.transfer_fee.get()
+    }
+
+    /// Sets the ETH every transfer must pay to the treasury, which has to be set first.
+    /// Zero makes transfers free.
+    pub fn set_transfer_fee(&mut self, fee: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if !fee.is_zero() && self.treasury.get().is_zero() {
+            return Err(b"No treasury".to_vec());
+        }
+        self.transfer_fee.set(fee);
+        Ok(())
+    }
+
+    /// Returns the contract that approves every transfer, `Address::ZERO` when transfers
+    /// aren't checked.
+    pub fn transfer_validator(&self) -> Address {
+        self.transfer_validator.get()
+    }
+
+    /// Sets the contract whose `canTransfer(from, to, tokenId)` must approve every
+    /// transfer. `Address::ZERO` turns the check off.
+    pub fn set_transfer_validator(&mut self, validator: Address) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.transfer_validator.set(validator);
+        Ok(())
+    }
+
+    /// Returns the operator filter registry and whether transfers consult it.
+    pub fn operator_filter(&self) -> (Address, bool) {
+        (self.operator_filter_registry.get(), self.operator_filter_enabled.get())
+    }
+
+    /// Registers the contract with an operator filter `registry`, subscribed to the
+    /// blocked operators of `subscription`, and starts consulting it on transfers.
+    pub fn register_operator_filter(&mut self, registry: Address, subscription: Address) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        let contract = self.vm().contract_address();
+        IOperatorFilterRegistry::new(registry).register_and_subscribe(&mut *self, contract, subscription)?;
+        self.operator_filter_registry.set(registry);
+        self.operator_filter_enabled.set(true);
+        Ok(())
+    }
+
+    /// Turns consulting the operator filter registry on transfers on or off.
+    pub fn set_operator_filter_enabled(&mut self, enabled: bool) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.operator_filter_enabled.set(enabled);
+        Ok(())
+    }
+
+    /// Returns the ERC-20 the contract collects fees in, swept by `drain` along with ETH.
+    pub fn fee_token(&self) -> Address {
+        self.fee_token.get()
+    }
+
+    /// Sets the ERC-20 the contract collects fees in. Zero means none.
+    pub fn set_fee_token(&mut self, token: Address) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.fee_token.set(token);
+        Ok(())
+    }
+
+    /// Sweeps the full ETH balance, and the fee token balance if one is set, to
+    /// `treasury`. Meant for retiring a deployment.
+    pub fn drain(&mut self, treasury: Address) -> Result<(), Vec<u8>> {
+        self._only_owner_without_multisig()?;
+        self._drain(treasury)
+    }
+
+    /// Returns the multisig signers and how many of them must confirm an action. A zero
+    /// threshold means there is no multisig.
+    pub fn multisig(&self) -> (Vec<Address>, U256) {
+        let list = &self.multisig_signers;
+        ((0..list.len()).filter_map(|i| list.get(i)).collect(), self.multisig_threshold.get())
+    }
+
+    /// Hands the sensitive actions over to `threshold` of `signers`. From then on they
+    /// only run through `propose_action`, `confirm_action` and `execute_action`.
+    pub fn set_multisig(&mut self, signers: Vec<Address>, threshold: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if !self.multisig_threshold.get().is_zero() {
+            return Err(b"Multisig active".to_vec());
+        }
+        if threshold.is_zero() || threshold > U256::from(signers.len()) {
+            return Err(b"Bad threshold".to_vec());
+        }
+        for signer in signers {
+            if signer.is_zero() || self.is_multisig_signer.get(signer) {
+                return Err(b"Bad signer".to_vec());
+            }
+            self.is_multisig_signer.setter(signer).set(true);
+            self.multisig_signers.push(signer);
+        }
+        self.multisig_threshold.set(threshold);
+        Ok(())
+    }
+
+    /// Proposes an action of `kind`, one of the `ACTION_*` values, with `target` as the
+    /// withdrawal recipient where it applies. Counts as the proposer's confirmation.
+    /// Returns the hash the other signers confirm it by. Kinds that take more than a
+    /// target have their own proposer.
+    pub fn propose_action(&mut self, kind: u8, target: Address) -> Result<FixedBytes<32>, Vec<u8>> {
+        self._only_multisig_signer()?;
+        if kind > ACTION_DRAIN {
+            return Err(b"Unknown action".to_vec());
+        }
+        self._propose_action(kind, target, Address::ZERO, "")
+    }
+
+    /// Proposes revealing the collection with `real_base_uri` and freezing it, like
+    /// `reveal_and_freeze`. Returns the action hash.
+    pub fn propose_reveal_and_freeze(&mut self, real_base_uri: String) -> Result<FixedBytes<32>, Vec<u8>> {
+        self._only_multisig_signer()?;
+        self._propose_action(ACTION_REVEAL_AND_FREEZE, Address::ZERO, Address::ZERO, &real_base_uri)
+    }
+
+    /// Proposes sending the contract's full balance of an ERC-20 `token` to `to`, like
+    /// `withdraw_erc20`. Returns the action hash.
+    pub fn propose_erc20_withdrawal(&mut self, token: Address, to: Address) -> Result<FixedBytes<32>, Vec<u8>> {
+        self._only_multisig_signer()?;
+        self._propose_action(ACTION_WITHDRAW_ERC20, to, token, "")
+    }
+
+    /// Confirms a proposed action on behalf of the calling signer.
+    pub fn confirm_action(&mut self, action_hash: FixedBytes<32>) -> Result<(), Vec<u8>> {
+        let signer = self._only_multisig_signer()?;
+        let mut action = self.actions.setter(action_hash);
+        if !action.proposed.get() || action.executed.get() {
+            return Err(b"Unknown action".to_vec());
+        }
+        if action.confirmed_by.get(signer) {
+            return Err(b"Already confirmed".to_vec());
+        }
+        action.confirmed_by.setter(signer).set(true);
+        let confirmations = action.confirmations.get() + U256::from(1);
+        action.confirmations.set(confirmations);
+        Ok(())
+    }
+
+    /// Returns how many signers confirmed an action, and whether it already ran.
+    pub fn action_status(&self, action_hash: FixedBytes<32>) -> (U256, bool) {
+        let action = self.actions.get(action_hash);
+        (action.confirmations.get(), action.executed.get())
+    }
+
+    /// Runs an action once `threshold` signers confirmed it. Each action runs once.
+    pub fn execute_action(&mut self, action_hash: FixedBytes<32>) -> Result<(), Vec<u8>> {
+        self._only_multisig_signer()?;
+        let action = self.actions.get(action_hash);
+        if !action.proposed.get() || action.executed.get() {
+            return Err(b"Unknown action".to_vec());
+        }
+        if action.confirmations.get() < self.multisig_threshold.get() {
+            return Err(b"Not enough confirmations".to_vec());
+        }
+        let kind = action.kind.get().to::<u8>();
+        let target = action.target.get();
+        self.actions.setter(action_hash).executed.set(true);
+
+        match kind {
+            ACTION_WITHDRAW => self._withdraw_to(target),
+            ACTION_APPLY_MAX_SUPPLY => self._apply_max_supply(),
+            ACTION_FREEZE_BASE_URI => {
+                self.base_uri_frozen.set(true);
+                Ok(())
+            }
+            ACTION_FINALIZE_SUPPLY => self._finalize_supply(),
+            ACTION_DRAIN => self._drain(target),
+            ACTION_REVEAL_AND_FREEZE => {
+                let uri = self.actions.get(action_hash).uri.get_string();
+                self._reveal_and_freeze(uri)
+            }
+            _ => {
+                let token = self.actions.get(action_hash).token.get();
+                self._withdraw_erc20(token, target)
+            }
+        }
+    }
+
+    /// Returns the EIP-712 domain separator signed permits are bound to.
+    #[selector(name = "DOMAIN_SEPARATOR")]
+    pub fn domain_separator(&self) -> FixedBytes<32> {
+        self.domain_separator_v4()
+    }
+
+    /// Returns the ERC-5267 description of the domain `DOMAIN_SEPARATOR` is built from:
+    /// the fields bitmap, name, version, chain id, verifying contract, salt and extensions.
+    pub fn eip712_domain(&self) -> (FixedBytes<1>, String, String, U256, Address, B256, Vec<U256>) {
+        let (fields, name, version, chain_id, verifying_contract, salt, extensions) = IEip712::eip712_domain(self);
+        (fields.into(), name, version, chain_id, verifying_contract, salt.into(), extensions)
+    }
+
+    /// Returns the nonce `owner` must sign into their next permit.
+    pub fn nonces(&self, owner: Address) -> U256 {
+        self.permit_nonces.get(owner)
+    }
+
+    /// Sets `operator`'s approval over all of `owner`'s tokens with an EIP-712
+    /// `PermitForAll` signature from `owner`, so the owner doesn't pay for gas.
+    /// `signature` is the 65 byte `r ++ s ++ v` encoding.
+    pub fn permit_for_all(
+        &mut self,
+        owner: Address,
+        operator: Address,
+        approved: bool,
+        deadline: U256,
+        signature: Bytes,
+    ) -> Result<(), Vec<u8>> {
+        if U256::from(self.vm().block_timestamp()) > deadline {
+            return Err(b"Permit expired".to_vec());
+        }
+
+        let nonce = self.permit_nonces.get(owner);
+        let struct_hash = keccak(
+            (FixedBytes::from(PERMIT_FOR_ALL_TYPEHASH), owner, operator, approved, nonce, deadline).abi_encode(),
+        );
+        let hash = self.hash_typed_data_v4(struct_hash);
+        if self._recover_signer(hash, &signature)? != owner {
+            return Err(b"Invalid signature".to_vec());
+        }
+
+        self.permit_nonces.setter(owner).set(nonce + U256::from(1));
+        self.erc721._set_approval_for_all(owner, operator, approved)?;
+        self._track_operator(owner, operator, approved);
+        Ok(())
+    }
+
+    /// Approves `operator` for each of `token_ids` with an EIP-712 `PermitBatch`
+    /// signature from `owner`, who must hold all of them. Uses the same nonces as
+    /// `permit_for_all`.
+    pub fn permit_batch(
+        &mut self,
+        owner: Address,
+        operator: Address,
+        token_ids: Vec<U256>,
+        deadline: U256,
+        signature: Bytes,
+    ) -> Result<(), Vec<u8>> {
+        if U256::from(self.vm().block_timestamp()) > deadline {
+            return Err(b"Permit expired".to_vec());
+        }
+        self._check_batch_size(token_ids.len())?;
+
+        let nonce = self.permit_nonces.get(owner);
+        let struct_hash = keccak(
+            (FixedBytes::from(PERMIT_BATCH_TYPEHASH), owner, operator, hash_token_ids(&token_ids), nonce, deadline)
+                .abi_encode(),
+        );
+        let hash = self.hash_typed_data_v4(struct_hash);
+        if self._recover_signer(hash, &signature)? != owner {
+            return Err(b"Invalid signature".to_vec());
+        }
+
+        self.permit_nonces.setter(owner).set(nonce + U256::from(1));
+        for token_id in token_ids {
+            if self._owner_of(token_id) != owner {
+                return Err(b"Not token owner".to_vec());
+            }
+            self.erc721._approve(operator, token_id, Address::ZERO, false)?;
+            log(self.vm(), Approval { owner, approved: operator, token_id });
+        }
+        Ok(())
+    }
+
+    /// Revokes every operator the caller approved for all of their tokens, through
+    /// `setApprovalForAll` or `permit_for_all`.
+    pub fn revoke_all_approvals(&mut self) -> Result<(), Vec<u8>> {
+        let owner = self.vm().msg_sender();
+        let list = self.approved_operators.get(owner);
+        let operators: Vec<Address> = (0..list.len()).filter_map(|i| list.get(i)).collect();
+
+        for operator in operators {
+            if self.erc721.is_approved_for_all(owner, operator) {
+                self.erc721._set_approval_for_all(owner, operator, false)?;
+            }
+            self.operator_tracked.setter(owner).setter(operator).set(false);
+        }
+        self.approved_operators.setter(owner).erase();
+        Ok(())
+    }
+
+    /// Repairs a token whose per-token state went wrong by clearing its lock and its
+    /// approval. The token's owner stays the same.
+    pub fn reset_token_state(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self._require_owned(token_id)?;
+        self.lock_until.delete(token_id);
+        self.erc721._approve(Address::ZERO, token_id, Address::ZERO, false)?;
+        log(self.vm(), TokenStateReset { tokenId: token_id });
+        Ok(())
+    }
+
+    /// Returns the account whose signed vouchers `redeem` accepts.
+    pub fn voucher_signer(&self) -> Address {
+        self.voucher_signer.get()
+    }
+
+    /// Sets the account whose signed vouchers `redeem` accepts. Zero disables vouchers.
+    pub fn set_voucher_signer(&mut self, signer: Address) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.voucher_signer.set(signer);
+        Ok(())
+    }
+
+    /// Returns the EIP-712 digest of the voucher for minting `uri` to `to`, which is
+    /// what the voucher signer signs and what `is_voucher_used` is keyed by.
+    pub fn voucher_hash(&self, to: Address, uri: String, nonce: U256) -> FixedBytes<32> {
+        let struct_hash = keccak((FixedBytes::from(VOUCHER_TYPEHASH), to, keccak(uri.as_bytes()), nonce).abi_encode());
+        self.hash_typed_data_v4(struct_hash)
+    }
+
+    /// Returns true if the voucher with this digest was already redeemed.
+    pub fn is_voucher_used(&self, voucher_hash: FixedBytes<32>) -> bool {
+        self.used_vouchers.get(voucher_hash)
+    }
+
+    /// Mints a token to `to` with a voucher signed by the voucher signer. Each voucher
+    /// can be redeemed once.
+    pub fn redeem(&mut self, to: Address, uri: String, nonce: U256, signature: Bytes) -> Result<U256, Vec<u8>> {
+        let voucher_signer = self.voucher_signer.get();
+        if voucher_signer.is_zero() {
+            return Err(b"Vouchers disabled".to_vec());
+        }
+        let hash = self.voucher_hash(to, uri.clone(), nonce);
+        if self.used_vouchers.get(hash) {
+            return Err(b"Voucher used".to_vec());
+        }
+        if self._recover_signer(hash, &signature)? != voucher_signer {
+            return Err(b"Invalid signature".to_vec());
+        }
+
+        self.used_vouchers.setter(hash).set(true);
+        self._mint_one(to, &uri)
+    }
+
+    /// Returns the account whose signatures `signed_allowlist_mint` accepts.
+    pub fn allow_signer(&self) -> Address {
+        self.allow_signer.get()
+    }
+
+    /// Replaces the allowlist signer. Signatures from the previous signer stop verifying
+    /// right away, while ones from `new_signer` work immediately. Zero disables signed
+    /// allowlist mints.
+    pub fn set_allow_signer(&mut self, new_signer: Address) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        let old_signer = self.allow_signer.get();
+        self.allow_signer.set(new_signer);
+        log(self.vm(), AllowSignerRotated { old_signer, new_signer });
+        Ok(())
+    }
+
+    /// Returns the EIP-712 digest the allowlist signer signs to let `minter` mint.
+    pub fn allowlist_mint_hash(&self, minter: Address) -> FixedBytes<32> {
+        let struct_hash = keccak((FixedBytes::from(ALLOWLIST_MINT_TYPEHASH), minter).abi_encode());
+        self.hash_typed_data_v4(struct_hash)
+    }
+
+    /// Returns how many tokens `minter` claimed through any allowlist: the stored one,
+    /// the signed one and the tiers.
+    pub fn allowlist_claimed(&self, minter: Address) -> U256 {
+        self.allowlist_claims.get(minter)
+    }
+
+    /// Mints a token to the caller for `allowlist_price`, with the allowlist signer's
+    /// signature over `allowlist_mint_hash(caller)` instead of a stored allowlist entry.
+    /// Each address can claim once.
+    #[payable]
+    pub fn signed_allowlist_mint(&mut self, uri: String, signature: Bytes) -> Result<U256, Vec<u8>> {
+        let allow_signer = self.allow_signer.get();
+        if allow_signer.is_zero() {
+            return Err(b"Signed allowlist disabled".to_vec());
+        }
+        let sender = self.vm().msg_sender();
+        if self.signed_allowlist_used.get(sender) {
+            return Err(b"Already claimed".to_vec());
+        }
+        let hash = self.allowlist_mint_hash(sender);
+        if self._recover_signer(hash, &signature)? != allow_signer {
+            return Err(b"Invalid signature".to_vec());
+        }
+        let payment = self._take_payment(self.allowlist_price()?)?;
+
+        self.signed_allowlist_used.setter(sender).set(true);
+        let token_id = self._mint_one(sender, &uri)?;
+        self._record_purchase(token_id, payment, false);
+        self._count_allowlist_claims(sender, U256::from(1));
+        Ok(token_id)
+    }
+
+    /// Accepts ERC-721 tokens sent with `safeTransferFrom`, so the contract can hold
+    /// other collections' tokens as well as its own.
+    #[selector(name = "onERC721Received")]
+    pub fn on_erc721_received(&mut self, _operator: Address, _from: Address, _token_id: U256, _data: Bytes) -> FixedBytes<4> {
+        FixedBytes(erc721::RECEIVER_FN_SELECTOR)
+    }
+
+    /// Stakes a token by moving it into the contract. The token's owner is recorded as
+    /// staker and is the only one who can unstake it. Staking runs the same checks as a
+    /// transfer, except the receiver allowlist and the transfer fee: the token can only
+    /// ever come back to the staker, so its holder doesn't change.
+    pub fn stake(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        if !self.stakes.get(token_id).staker.get().is_zero() {
+            return Err(b"Already staked".to_vec());
+        }
+
+        let caller = self.vm().msg_sender();
+        let contract = self.vm().contract_address();
+        self._check_transfer(self._owner_of(token_id), contract, token_id)?;
+        let staker = self._update(contract, token_id, caller)?;
+
+        let mut list = self.staked_tokens.setter(staker);
+        let list_index = U256::from(list.len());
+        list.push(token_id);
+
+        let timestamp = U256::from(self.vm().block_timestamp());
+        let mut stake = self.stakes.setter(token_id);
+        stake.staker.set(staker);
+        stake.staked_at.set(timestamp);
+        stake.rewards_from.set(timestamp);
+        stake.list_index.set(list_index);
+        Ok(())
+    }
+
+    /// Returns a staked token to its staker, paying out any rewards it has accrued.
+    pub fn unstake(&mut self, token_id: U256) -> Result<(), Vec<u8>> {
+        let staker = self.stakes.get(token_id).staker.get();
+        if staker.is_zero() {
+            return Err(b"Not staked".to_vec());
+        }
+        if staker != self.vm().msg_sender() {
+            return Err(b"Not staker".to_vec());
+        }
+        if !self.reward_token.get().is_zero() {
+            self._pay_rewards(token_id, staker)?;
+        }
+
+        // Swap the last of the staker's tokens into this one's slot and drop the tail.
+        let index = self.stakes.get(token_id).list_index.get();
+        let mut list = self.staked_tokens.setter(staker);
+        let last = list.len() - 1;
+        let moved = list.get(last).unwrap();
+        list.setter(index).unwrap().set(moved);
+        list.pop();
+        self.stakes.setter(moved).list_index.set(index);
+
+        let mut stake = self.stakes.setter(token_id);
+        stake.staker.set(Address::ZERO);
+        stake.staked_at.set(U256::ZERO);
+        stake.rewards_from.set(U256::ZERO);
+        stake.list_index.set(U256::ZERO);
+        self._update(staker, token_id, Address::ZERO)?;
+        Ok(())
+    }
+
+    /// Returns the ids of all tokens `owner` holds, in no particular order.
+    pub fn tokens_of_owner(&self, owner: Address) -> Vec<U256> {
+        let list = self.owned_tokens.get(owner);
+        (0..list.len()).filter_map(|i| list.get(i)).collect()
+    }
+
+    /// Returns the token at `index` in `owner`'s token list.
+    pub fn token_of_owner_by_index(&self, owner: Address, index: U256) -> Result<U256, Vec<u8>> {
+        self.owned_tokens.get(owner).get(index).ok_or_else(|| b"Index out of bounds".to_vec())
+    }
+
+    /// Takes a snapshot of every balance at the current block, for `balance_of_at`.
+    /// Returns the snapshot id, counting from 1.
+    pub fn snapshot(&mut self) -> Result<U256, Vec<u8>> {
+        self._only_owner()?;
+        let id = self.snapshot_id.get() + U256::from(1);
+        self.snapshot_id.set(id);
+        let block = U256::from(self.vm().block_number());
+        self.snapshot_blocks.setter(id).set(block);
+        Ok(id)
+    }
+
+    /// Returns the id of the latest snapshot, zero before the first one.
+    pub fn current_snapshot_id(&self) -> U256 {
+        self.snapshot_id.get()
+    }
+
+    /// Returns the block number a snapshot was taken at.
+    pub fn snapshot_block(&self, snapshot_id: U256) -> U256 {
+        self.snapshot_blocks.get(snapshot_id)
+    }
+
+    /// Returns the balance `owner` had when snapshot `snapshot_id` was taken.
+    pub fn balance_of_at(&self, owner: Address, snapshot_id: U256) -> Result<U256, Vec<u8>> {
+        let current = self.snapshot_id.get();
+        if snapshot_id.is_zero() || snapshot_id > current {
+            return Err(b"Unknown snapshot".to_vec());
+        }
+        // A balance is recorded at the first change after a snapshot. If that was after
+        // a later snapshot, it still held at this one.
+        let mut id = snapshot_id;
+        while id <= current {
+            if self.snapshot_recorded.get(id).get(owner) {
+                return Ok(self.snapshot_balances.get(id).get(owner));
+            }
+            id += U256::from(1);
+        }
+        Ok(self._balance(owner))
+    }
+
+    /// Returns the ids of all tokens currently staked by `staker`, in no particular order.
+    pub fn staked_tokens_of(&self, staker: Address) -> Vec<U256> {
+        let list = self.staked_tokens.get(staker);
+        (0..list.len()).filter_map(|i| list.get(i)).collect()
+    }
+
+    /// Returns the account that staked a token, or `Address::ZERO` if it isn't staked.
+    pub fn staker_of(&self, token_id: U256) -> Address {
+        self.stakes.get(token_id).staker.get()
+    }
+
+    /// Returns how many seconds a token has been staked for, or zero if it isn't staked.
+    pub fn staked_duration(&self, token_id: U256) -> U256 {
+        let stake = self.stakes.get(token_id);
+        if stake.staker.get().is_zero() {
+            return U256::ZERO;
+        }
+        U256::from(self.vm().block_timestamp()) - stake.staked_at.get()
+    }
+
+    /// Returns the ERC-20 token paid out to stakers and its rate per staked second.
+    pub fn staking_rewards(&self) -> (Address, U256) {
+        (self.reward_token.get(), self.reward_rate_per_second.get())
+    }
+
+    /// Sets the ERC-20 reward token and how much of it a staked token earns per second.
+    /// The contract must hold enough of the token to pay out claims.
+    pub fn set_staking_rewards(&mut self, token: Address, rate_per_second: U256) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        self.reward_token.set(token);
+        self.reward_rate_per_second.set(rate_per_second);
+        Ok(())
+    }
+
+    /// Returns the rewards a staked token has accrued since they were last claimed.
+    pub fn pending_rewards(&self, token_id: U256) -> U256 {
+        let stake = self.stakes.get(token_id);
+        if stake.staker.get().is_zero() {
+            return U256::ZERO;
+        }
+        let elapsed = U256::from(self.vm().block_timestamp()) - stake.rewards_from.get();
+        elapsed * self.reward_rate_per_second.get()
+    }
+
+    /// Pays the rewards accrued by a staked token to its staker and restarts accrual.
+    pub fn claim_rewards(&mut self, token_id: U256) -> Result<U256, Vec<u8>> {
+        let staker = self.stakes.get(token_id).staker.get();
+        if staker.is_zero() {
+            return Err(b"Not staked".to_vec());
+        }
+        if staker != self.vm().msg_sender() {
+            return Err(b"Not staker".to_vec());
+        }
+        let token = self.reward_token.get();
+        if token.is_zero() {
+            return Err(b"Rewards disabled".to_vec());
+        }
+        self._pay_rewards(token_id, staker)
+    }
+}
+
+impl DEMONFT {
+    /// Sends `staker` the rewards `token_id` has accrued and restarts its accrual.
+    fn _pay_rewards(&mut self, token_id: U256, staker: Address) -> Result<U256, Vec<u8>> {
+        let amount = self.pending_rewards(token_id);
+        let timestamp = U256::from(self.vm().block_timestamp());
+        self.stakes.setter(token_id).rewards_from.set(timestamp);
+
+        let token = self.reward_token.get();
+        if !amount.is_zero() && !IErc20::new(token).transfer(&mut *self, staker, amount)? {
+            return Err(b"ERC20 transfer failed".to_vec());
+        }
+        Ok(amount)
+    }
+
+    /// Remembers `operator` as approved by `owner` for `revoke_all_approvals`.
+    fn _track_operator(&mut self, owner: Address, operator: Address, approved: bool) {
+        if approved && !self.operator_tracked.get(owner).get(operator) {
+            self.operator_tracked.setter(owner).setter(operator).set(true);
+            self.approved_operators.setter(owner).push(operator);
+        }
+    }
+
+    /// Replaces the default royalty of `token_id` with `bps` paid to `receiver`.
+    fn _set_token_royalty(&mut self, token_id: U256, receiver: Address, bps: U256) -> Result<(), Vec<u8>> {
+        if receiver.is_zero() {
+            return Err(b"Zero address".to_vec());
+        }
+        if bps > U256::from(10_000) {
+            return Err(b"Royalty too high".to_vec());
+        }
+        if self.token_royalties.get(token_id).receiver.get().is_zero() {
+            self.custom_royalty_count.set(self.custom_royalty_count.get() + U256::from(1));
+        }
+        let mut royalty = self.token_royalties.setter(token_id);
+        royalty.receiver.set(receiver);
+        royalty.bps.set(bps);
+        log(self.vm(), TokenRoyaltySet { tokenId: token_id, receiver, bps });
+        Ok(())
+    }
+
+    /// Returns the contract's ETH balance minus royalties waiting to be claimed, zero
+    /// if the balance falls short of them.
+    fn _free_balance(&self) -> U256 {
+        self.vm().balance(self.vm().contract_address()).saturating_sub(self.total_pending_royalties.get())
+    }
+
+    /// Reverts unless the caller is the contract owner.
+    fn _only_owner(&self) -> Result<(), Vec<u8>> {
+        if self.vm().msg_sender() != self.owner.get() {
+            return Err(b"Not owner".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Emits `BatchMetadataUpdate` up to the highest minted id so marketplaces re-fetch
+    /// metadata.
+    fn _refresh_all_metadata(&mut self) {
+        if self.minted_count.get().is_zero() {
+            return;
+        }
+        let highest_id = self.highest_id.get();
+        let from = self.start_id.get().min(highest_id);
+        log(self.vm(), BatchMetadataUpdate { _fromTokenId: from, _toTokenId: highest_id });
+    }
+
+    /// Reverts once royalties are frozen.
+    fn _check_royalties_not_frozen(&self) -> Result<(), Vec<u8>> {
+        if self.royalties_frozen.get() {
+            return Err(b"Royalties frozen".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Reverts once the base URI is frozen.
+    fn _check_base_uri_not_frozen(&self) -> Result<(), Vec<u8>> {
+        if self.base_uri_frozen.get() {
+            return Err(b"Base URI frozen".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Reverts while the operator filter is on and its registry blocks the caller from
+    /// moving `from`'s tokens. Owners moving their own tokens are never filtered.
+    fn _check_operator_filter(&self, from: Address) -> Result<(), Vec<u8>> {
+        let registry = self.operator_filter_registry.get();
+        let operator = self.vm().msg_sender();
+        if !self.operator_filter_enabled.get() || registry.is_zero() || operator == from {
+            return Ok(());
+        }
+        let contract = self.vm().contract_address();
+        if !IOperatorFilterRegistry::new(registry).is_operator_allowed(self, contract, operator)? {
+            return Err(b"Operator not allowed".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Reverts unless the caller is the owner and no multisig has taken over the
+    /// sensitive actions.
+    fn _only_owner_without_multisig(&self) -> Result<(), Vec<u8>> {
+        self._only_owner()?;
+        if !self.multisig_threshold.get().is_zero() {
+            return Err(b"Multisig required".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Reverts unless the caller is a multisig signer. Returns the caller.
+    fn _only_multisig_signer(&self) -> Result<Address, Vec<u8>> {
+        let caller = self.vm().msg_sender();
+        if !self.is_multisig_signer.get(caller) {
+            return Err(b"Not signer".to_vec());
+        }
+        Ok(caller)
+    }
+
+    /// Sends the contract's ETH balance to `to`, minus royalties waiting to be claimed.
+    fn _withdraw_to(&mut self, to: Address) -> Result<(), Vec<u8>> {
+        if to.is_zero() {
+            return Err(b"Zero address".to_vec());
+        }
+
+        let amount = self._free_balance();
+        self.vm().transfer_eth(to, amount)?;
+        log(self.vm(), EthWithdrawn { to, amount });
+        Ok(())
+    }
+
+    /// Records a new action of `kind` and confirms it for the calling signer. `token` and
+    /// `uri` are only read by the kinds that need them.
+    fn _propose_action(&mut self, kind: u8, target: Address, token: Address, uri: &str) -> Result<FixedBytes<32>, Vec<u8>> {
+        let nonce = self.action_nonce.get();
+        self.action_nonce.set(nonce + U256::from(1));
+        let action_hash = keccak((U256::from(kind), target, nonce, self.vm().contract_address()).abi_encode());
+
+        let mut action = self.actions.setter(action_hash);
+        action.proposed.set(true);
+        action.kind.set(stylus_sdk::alloy_primitives::U8::from(kind));
+        action.target.set(target);
+        action.token.set(token);
+        action.uri.set_str(uri);
+        self.confirm_action(action_hash)?;
+        Ok(action_hash)
+    }
+
+    /// Reveals the collection with `real_base_uri` and freezes the base URI.
+    fn _reveal_and_freeze(&mut self, real_base_uri: String) -> Result<(), Vec<u8>> {
+        if self.is_revealed() {
+            return Err(b"Already revealed".to_vec());
+        }
+        self._check_base_uri_not_frozen()?;
+        self._check_uri_length(real_base_uri.as_bytes())?;
+
+        self.revealed.set(true);
+        self.base_uri.set_str(real_base_uri.as_str());
+        self.base_uri_frozen.set(true);
+        log(self.vm(), Revealed { base_uri: real_base_uri });
+        self._refresh_all_metadata();
+        Ok(())
+    }
+
+    /// Sends the contract's full balance of an ERC-20 `token` to `to`.
+    fn _withdraw_erc20(&mut self, token: Address, to: Address) -> Result<(), Vec<u8>> {
+        if to.is_zero() {
+            return Err(b"Zero address".to_vec());
+        }
+
+        let erc20 = IErc20::new(token);
+        let amount = erc20.balance_of(&*self, self.vm().contract_address())?;
+        if !erc20.transfer(&mut *self, to, amount)? {
+            return Err(b"ERC20 transfer failed".to_vec());
+        }
+        log(self.vm(), Erc20Withdrawn { token, to, amount });
+        Ok(())
+    }
+
+    /// Sweeps the free ETH balance, and the fee token balance if one is set, to `treasury`.
+    fn _drain(&mut self, treasury: Address) -> Result<(), Vec<u8>> {
+        self._withdraw_to(treasury)?;
+        let fee_token = self.fee_token.get();
+        if !fee_token.is_zero() {
+            self._withdraw_erc20(fee_token, treasury)?;
+        }
+        Ok(())
+    }
+
+    /// Reverts while the contract is paused.
+    fn _when_not_paused(&self) -> Result<(), Vec<u8>> {
+        if self.paused() {
+            return Err(b"Paused".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Reverts if `to` is a contract while contract recipients are blocked.
+    fn _check_recipient(&self, to: Address) -> Result<(), Vec<u8>> {
+        if self.block_contract_recipients.get() {
+            let code_hash = self.vm().code_hash(to);
+            if !code_hash.is_zero() && code_hash != EMPTY_CODE_HASH {
+                return Err(b"Contract recipient".to_vec());
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds `quantity` to the tokens `minter` claimed through allowlists.
+    fn _count_allowlist_claims(&mut self, minter: Address, quantity: U256) {
+        let claimed = self.allowlist_claims.get(minter);
+        self.allowlist_claims.setter(minter).set(claimed + quantity);
+    }
+
+    /// Runs the checks every transfer of `token_id` from `from` to `to` must pass: pause,
+    /// hold period, lock, transfer validator and operator filter.
+    fn _check_transfer(&mut self, from: Address, to: Address, token_id: U256) -> Result<(), Vec<u8>> {
+        self._when_not_paused()?;
+        let min_hold = self.min_hold_seconds.get();
+        if !min_hold.is_zero() && U256::from(self.vm().block_timestamp()) - self.acquired_at.get(token_id) < min_hold {
+            return Err(b"Hold period active".to_vec());
+        }
+        if self.is_locked(token_id) {
+            return Err(b"Token locked".to_vec());
+        }
+        let validator = self.transfer_validator.get();
+        if !validator.is_zero() && !ITransferValidator::new(validator).can_transfer(&*self, from, to, token_id)? {
+            return Err(b"Transfer denied".to_vec());
+        }
+        self._check_operator_filter(from)
+    }
+
+    /// Reverts if the receiver allowlist is on and `to` isn't allowed on it.
+    fn _check_receiver_allowed(&self, to: Address) -> Result<(), Vec<u8>> {
+        if self.receiver_allowlist_enabled.get() && !self.is_allowed_receiver(to) {
+            return Err(b"Receiver not allowed".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Reverts if a batch of `len` items is larger than `max_batch_size`.
+    fn _check_batch_size(&self, len: usize) -> Result<(), Vec<u8>> {
+        let max = self.max_batch_size.get();
+        if !max.is_zero() && U256::from(len) > max {
+            return Err(b"Batch too large".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Reverts with `MaxSupplyReached` if minting `quantity` more tokens would pass the
+    /// cap, leaving room for the unminted reserve.
+    fn _check_supply(&self, quantity: U256) -> Result<(), Vec<u8>> {
+        let minted = self.minted_count.get();
+        let max_supply = self.max_supply.get();
+        if max_supply.is_zero() {
+            return Ok(());
+        }
+        let supply_cap = max_supply.saturating_sub(self.reserved_remaining.get());
+        if minted + quantity > supply_cap {
+            return Err(DemoNftError::MaxSupplyReached(MaxSupplyReached { minted, cap: supply_cap }).into());
+        }
+        Ok(())
+    }
+
+    /// Sets `max_supply` to the minted count and drops the reserve and any proposal.
+    fn _finalize_supply(&mut self) -> Result<(), Vec<u8>> {
+        let minted = self.minted_count.get();
+        // A zero `max_supply` would lift the cap instead.
+        if minted.is_zero() {
+            return Err(b"Nothing minted".to_vec());
+        }
+        self.reserved_remaining.set(U256::ZERO);
+        self.max_supply.set(minted);
+        self.pending_max_supply.set(U256::ZERO);
+        self.max_supply_unlock_at.set(U256::ZERO);
+        log(self.vm(), CollectionFinalized { totalSupply: minted });
+        Ok(())
+    }
+
+    /// Sets `max_supply` to the proposed value once its timelock has passed.
+    fn _apply_max_supply(&mut self) -> Result<(), Vec<u8>> {
+        let unlock_at = self.max_supply_unlock_at.get();
+        if unlock_at.is_zero() {
+            return Err(b"No proposal".to_vec());
+        }
+        if U256::from(self.vm().block_timestamp()) < unlock_at {
+            return Err(b"Timelock active".to_vec());
+        }
+        // Tokens may have been minted while the proposal waited.
+        let new_max = self.pending_max_supply.get();
+        self._check_max_supply(new_max)?;
+        self.max_supply.set(new_max);
+        self.pending_max_supply.set(U256::ZERO);
+        self.max_supply_unlock_at.set(U256::ZERO);
+        Ok(())
+    }
+
+    /// Reverts if `max_supply` can't be `new_max` because more tokens were already minted
+    /// or reserved.
+    fn _check_max_supply(&self, new_max: U256) -> Result<(), Vec<u8>> {
+        if !new_max.is_zero() && new_max < self.minted_count.get() + self.reserved_remaining.get() {
+            return Err(b"Below minted".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Adds `quantity` to the current block's mint count, reverting once it would pass
+    /// `max_mints_per_block`. Nothing is counted while there is no cap.
+    fn _count_block_mints(&mut self, quantity: U256) -> Result<(), Vec<u8>> {
+        let cap = self.max_mints_per_block.get();
+        if cap.is_zero() {
+            return Ok(());
+        }
+        let block = U256::from(self.vm().block_number());
+        let minted = self.block_mints.get(block) + quantity;
+        if minted > cap {
+            return Err(b"Block mint cap".to_vec());
+        }
+        self.block_mints.setter(block).set(minted);
+        Ok(())
+    }
+
+    /// Returns the first id from `from` on that isn't listed for sale, already sold
+    /// individually or burned.
+    fn _next_sequential_id(&self, from: U256) -> U256 {
+        let mut id = from;
+        while !self.owners.get(id).is_zero() || self.burned.get(id) || !self.token_price.get(id).is_zero() {
+            id += U256::from(1);
+        }
+        id
+    }
+
+    /// Resolves the owner of a token, walking back through its batch run when the
+    /// owner was never written for this exact id. Returns `Address::ZERO` when unminted.
+    fn _owner_of(&self, token_id: U256) -> Address {
+        if self.burned.get(token_id) {
+            return Address::ZERO;
+        }
+        // Tokens minted before ownership moved out of `erc721` keep their owner there
+        // until they first move.
+        let legacy_owner = self.erc721._owner_of(token_id);
+        if !legacy_owner.is_zero() && self.owners.get(token_id).is_zero() {
+            return legacy_owner;
+        }
+        // Outside the sequential range only individually minted ids exist, and those
+        // always have their owner written.
+        if token_id >= self.next_id.get() || token_id < self.start_id.get() {
+            return self.owners.get(token_id);
+        }
+        // Every run starts with a recorded owner, so this stops at the run's first id
+        // at the latest. Ids skipped by `set_next_id` aren't in any run: walking back
+        // from one reaches an explicitly minted id or the start of the skipped range.
+        let mut id = token_id;
+        loop {
+            let owner = self.owners.get(id);
+            if !owner.is_zero() {
+                if id != token_id && self.minted_explicitly.get(id) {
+                    return Address::ZERO;
+                }
+                return owner;
+            }
+            if id.is_zero() || self.run_breaks.get(id) {
+                return owner;
+            }
+            id -= U256::from(1);
+        }
+    }
+
+    /// Returns the owner of a token, or `NonexistentToken` if it was never minted or
+    /// was burned. Every existence check goes through this so callers see one error.
+    fn _require_owned(&self, token_id: U256) -> Result<Address, Vec<u8>> {
+        let owner = self._owner_of(token_id);
+        if owner.is_zero() {
+            return Err(DemoNftError::NonexistentToken(NonexistentToken { id: token_id }).into());
+        }
+        Ok(owner)
+    }
+
+    /// Moves `token_id` to `to` (or mints it when it has no owner yet), checking that
+    /// `auth` may operate on it unless `auth` is zero. Returns the previous owner.
+    fn _update(&mut self, to: Address, token_id: U256, auth: Address) -> Result<Address, erc721::Error> {
+        let from = self._owner_of(token_id);
+
+        if !auth.is_zero() {
+            self.erc721._check_authorized(from, auth, token_id)?;
+        }
+
+        if !from.is_zero() {
+            // Clear approval. No need to re-authorize or emit the `Approval` event.
+            self.erc721._approve(Address::ZERO, token_id, Address::ZERO, false)?;
+            self._snapshot_balance(from);
+            self._move_legacy_balance(from);
+            let balance = self.balances.get(from);
+            self.balances.setter(from).set(balance - U256::from(1));
+
+            // If the next id still inherits its owner from this one, pin it before
+            // this token leaves the run. Legacy tokens are in no run.
+            let next = token_id + U256::from(1);
+            let in_run = !self.minted_explicitly.get(token_id) && !self.run_breaks.get(next);
+            if in_run
+                && next < self.next_id.get()
+                && self.owners.get(next).is_zero()
+                && self.erc721._owner_of(next).is_zero()
+            {
+                self.owners.setter(next).set(from);
+            }
+            self._remove_owned(from, token_id);
+        }
+
+        if !to.is_zero() {
+            self._snapshot_balance(to);
+            self._move_legacy_balance(to);
+            let balance = self.balances.get(to);
+            self.balances.setter(to).set(balance + U256::from(1));
+            self._add_owned(to, token_id);
+        }
+
+        self.owners.setter(token_id).set(to);
+        let now = U256::from(self.vm().block_timestamp());
+        self.acquired_at.setter(token_id).set(now);
+        if from.is_zero() {
+            self.minted_at.setter(token_id).set(now);
+            self._assign_seed(token_id, now);
+        }
+        log(self.vm(), Transfer { from, to, token_id });
+        Ok(from)
+    }
+
+    /// Records `owner`'s balance for the latest snapshot before it first changes after
+    /// that snapshot.
+    fn _snapshot_balance(&mut self, owner: Address) {
+        let id = self.snapshot_id.get();
+        if id.is_zero() || self.snapshot_recorded.get(id).get(owner) {
+            return;
+        }
+        let balance = self._balance(owner);
+        self.snapshot_balances.setter(id).setter(owner).set(balance);
+        self.snapshot_recorded.setter(id).setter(owner).set(true);
+    }
+
+    /// Returns how many tokens `owner` holds, counting legacy tokens still recorded in
+    /// `erc721`.
+    fn _balance(&self, owner: Address) -> U256 {
+        let balance = self.balances.get(owner);
+        if self.legacy_balance_moved.get(owner) {
+            return balance;
+        }
+        balance + self.erc721.balance_of(owner).unwrap_or_default()
+    }
+
+    /// Adds `owner`'s legacy `erc721` balance to `balances` the first time it changes.
+    fn _move_legacy_balance(&mut self, owner: Address) {
+        if self.legacy_balance_moved.get(owner) {
+            return;
+        }
+        // Nothing writes `erc721` balances any more, so a zero one never needs moving.
+        let legacy = self.erc721.balance_of(owner).unwrap_or_default();
+        if legacy.is_zero() {
+            return;
+        }
+        self.legacy_balance_moved.setter(owner).set(true);
+        let balance = self.balances.get(owner);
+        self.balances.setter(owner).set(balance + legacy);
+    }
+
+    /// Stores `keccak256(abi.encode(timestamp, minter, token_id))` as the seed of a token
+    /// just minted at `timestamp`.
+    fn _assign_seed(&mut self, token_id: U256, timestamp: U256) {
+        let minter = self.vm().msg_sender();
+        let seed = keccak((timestamp, minter, token_id).abi_encode());
+        self.token_seed.setter(token_id).set(seed);
+    }
+
+    /// Appends `token_id` to `owner`'s token list.
+    fn _add_owned(&mut self, owner: Address, token_id: U256) {
+        let mut list = self.owned_tokens.setter(owner);
+        let index = U256::from(list.len());
+        list.push(token_id);
+        self.owned_index.setter(token_id).set(index);
+    }
+
+    /// Removes `token_id` from `owner`'s token list by moving the list's last token into
+    /// its slot. Tokens that aren't listed, e.g. ones minted before the lists existed,
+    /// are left alone.
+    fn _remove_owned(&mut self, owner: Address, token_id: U256) {
+        let index = self.owned_index.get(token_id);
+        let mut list = self.owned_tokens.setter(owner);
+        if list.get(index) != Some(token_id) {
+            return;
+        }
+        let last = list.len() - 1;
+        let moved = list.get(last).unwrap();
+        list.setter(index).unwrap().set(moved);
+        list.pop();
+        self.owned_index.setter(moved).set(index);
+        self.owned_index.setter(token_id).set(U256::ZERO);
+    }
+
+    /// Mints a single token to `to`.
+    fn _mint(&mut self, to: Address, token_id: U256) -> Result<(), erc721::Error> {
+        if to.is_zero() {
+            return Err(ERC721InvalidReceiver { receiver: Address::ZERO }.into());
+        }
+
+        let previous_owner = self._update(to, token_id, Address::ZERO)?;
+        if !previous_owner.is_zero() {
+            return Err(ERC721InvalidSender { sender: Address::ZERO }.into());
+        }
+        Ok(())
+    }
+
+    /// Burns `token_id`, checking that `auth` may operate on it. Burned ids are never
+    /// minted again.
+    fn _burn(&mut self, token_id: U256, auth: Address) -> Result<(), Vec<u8>> {
+        let previous_owner = self._update(Address::ZERO, token_id, auth)?;
+        if previous_owner.is_zero() {
+            return Err(DemoNftError::NonexistentToken(NonexistentToken { id: token_id }).into());
+        }
+        self.burned.setter(token_id).set(true);
+        self.burned_count.set(self.burned_count.get() + U256::from(1));
+        self.token_uris.delete(token_id);
+        Ok(())
+    }
+
+    /// Recovers who signed `hash` from a 65 byte `r ++ s ++ v` signature. Every `hash`
+    /// passed here comes from `hash_typed_data_v4`, so it's bound to this contract and
+    /// the current chain id, and a signature made for the same contract on another chain
+    /// recovers to some other address.
+    fn _recover_signer(&mut self, hash: FixedBytes<32>, signature: &Bytes) -> Result<Address, Vec<u8>> {
+        if signature.len() != 65 {
+            return Err(b"Invalid signature".to_vec());
+        }
+        let r = FixedBytes::from_slice(&signature[..32]);
+        let s = FixedBytes::from_slice(&signature[32..64]);
+        Ok(ecdsa::recover(self, hash, signature[64], r, s)?)
+    }
+
+    /// Checks every minting rule for minting `quantity` tokens to `to`: pause, recipient,
+    /// supply cap, per-block cap and the caller's cooldown. Every mint path goes through
+    /// this once per call.
+    fn _before_mint(&mut self, to: Address, quantity: U256) -> Result<(), Vec<u8>> {
+        self._when_not_paused()?;
+        // Nothing could ever move a token minted to the contract itself. Staked tokens
+        // are transferred in instead.
+        if to == self.vm().contract_address() {
+            return Err(b"Cannot mint to self".to_vec());
+        }
+        self._check_recipient(to)?;
+        self._check_receiver_allowed(to)?;
+        self._check_supply(quantity)?;
+        self._count_block_mints(quantity)?;
+        self._start_mint_cooldown()
+    }
+
+    /// Reverts if the caller minted less than `mint_cooldown` seconds ago, then restarts
+    /// their cooldown. The owner has no cooldown, so airdrops aren't rate limited.
+    fn _start_mint_cooldown(&mut self) -> Result<(), Vec<u8>> {
+        let cooldown = self.mint_cooldown.get();
+        let caller = self.vm().msg_sender();
+        if cooldown.is_zero() || caller == self.owner.get() {
+            return Ok(());
+        }
+        if self._in_mint_cooldown(caller) {
+            return Err(b"Mint cooldown".to_vec());
+        }
+        let now = U256::from(self.vm().block_timestamp());
+        self.last_mint_at.setter(caller).set(now);
+        Ok(())
+    }
+
+    /// Returns true if `who` minted less than `mint_cooldown` seconds ago.
+    fn _in_mint_cooldown(&self, who: Address) -> bool {
+        let last = self.last_mint_at.get(who);
+        let now = U256::from(self.vm().block_timestamp());
+        !last.is_zero() && now < last + self.mint_cooldown.get()
+    }
+
+    /// Returns why minting `quantity` tokens to `who` would revert, following the checks
+    /// of `_before_mint` and `_take_payment`.
+    fn _mint_blocker(&self, who: Address, quantity: U256) -> Option<&'static str> {
+        if self.paused() {
+            return Some("Paused");
+        }
+        if who == self.vm().contract_address() {
+            return Some("Cannot mint to self");
+        }
+        if self._check_recipient(who).is_err() {
+            return Some("Contract recipient");
+        }
+        if self._check_receiver_allowed(who).is_err() {
+            return Some("Receiver not allowed");
+        }
+        if self._check_supply(quantity).is_err() {
+            return Some("Max supply reached");
+        }
+        let block_cap = self.max_mints_per_block.get();
+        if !block_cap.is_zero() && self.mints_this_block() + quantity > block_cap {
+            return Some("Block mint cap");
+        }
+        if !self.mint_cooldown.get().is_zero() && who != self.owner.get() && self._in_mint_cooldown(who) {
+            return Some("Mint cooldown");
+        }
+        // `public_mint` charges everyone the public price, credits and allowlist aside.
+        let Ok(price) = self.public_mint_price() else {
+            return Some("Bad oracle");
+        };
+        let spend_cap = self.max_spend_per_wallet.get();
+        if !spend_cap.is_zero() && self.spent_by.get(who) + price * quantity > spend_cap {
+            return Some("Spend cap reached");
+        }
+        None
+    }
+
+    /// Records the caller, what the contract kept of their payment (`paid`) and whether
+    /// that was `forwarded` to the treasury, as the purchase of a just minted token.
+    fn _record_purchase(&mut self, token_id: U256, paid: U256, forwarded: bool) {
+        let buyer = self.vm().msg_sender();
+        let minted_at = U256::from(self.vm().block_timestamp());
+        let mut purchase = self.purchases.setter(token_id);
+        purchase.buyer.set(buyer);
+        purchase.paid.set(paid);
+        purchase.minted_at.set(minted_at);
+        purchase.forwarded.set(forwarded);
+    }
+
+    /// Checks that the attached ETH covers `price` and adds it to the caller's spending,
+    /// reverting once that would pass `max_spend_per_wallet`. Sends the platform fee out
+    /// of it and returns what is left for the collection.
+    fn _take_payment(&mut self, price: U256) -> Result<U256, Vec<u8>> {
+        let payment = self.vm().msg_value();
+        if payment < price {
+            return Err(b"Insufficient payment".to_vec());
+        }
+        let sender = self.vm().msg_sender();
+        let spent = self.spent_by.get(sender) + payment;
+        let cap = self.max_spend_per_wallet.get();
+        if !cap.is_zero() && spent > cap {
+            return Err(b"Spend cap reached".to_vec());
+        }
+        self.spent_by.setter(sender).set(spent);
+
+        let fee = self._platform_fee(payment);
+        if !fee.is_zero() {
+            self.vm().transfer_eth(self.platform_fee_recipient.get(), fee)?;
+        }
+        Ok(payment - fee)
+    }
+
+    /// Returns the platform's cut of a payment of `amount`.
+    fn _platform_fee(&self, amount: U256) -> U256 {
+        amount * self.platform_fee_bps.get() / U256::from(10_000)
+    }
+
+    /// Approves the auto-approve operator for a token just minted to `to`, while that
+    /// is switched on.
+    fn _auto_approve(&mut self, to: Address, token_id: U256) -> Result<(), Vec<u8>> {
+        if !self.auto_approve.get() {
+            return Ok(());
+        }
+        let operator = self.auto_approve_operator.get();
+        self.erc721._approve(operator, token_id, Address::ZERO, false)?;
+        log(self.vm(), Approval { owner: to, approved: operator, token_id });
+        Ok(())
+    }
+
+    /// Records `quantity` tokens minted to `to`, the largest of them being `last_id`.
+    /// Every mint path goes through this once its tokens exist.
+    fn _after_mint(&mut self, to: Address, last_id: U256, quantity: U256) {
+        if self.minted_count.get().is_zero() || last_id > self.highest_id.get() {
+            self.highest_id.set(last_id);
+        }
+        let minted = self.minted_count.get() + quantity;
+        self.minted_count.set(minted);
+        self.has_minted.setter(to).set(true);
+        self._emit_supply_milestones(minted);
+    }
+
+    /// Emits `SupplyMilestone` for every quarter of `max_supply` that `minted` reached
+    /// and that wasn't announced before.
+    fn _emit_supply_milestones(&mut self, minted: U256) {
+        let max_supply = self.max_supply.get();
+        if max_supply.is_zero() {
+            return;
+        }
+        let emitted = self.supply_milestones.get().to::<u8>();
+        let mut reached = emitted;
+        for quarter in 1..=4_u8 {
+            let bit = 1 << (quarter - 1);
+            if emitted & bit == 0 && minted * U256::from(4) >= max_supply * U256::from(quarter) {
+                reached |= bit;
+                let percent_bps = U256::from(quarter) * U256::from(2_500);
+                log(self.vm(), SupplyMilestone { percentBps: percent_bps, minted });
+            }
+        }
+        if reached != emitted {
+            self.supply_milestones.set(stylus_sdk::alloy_primitives::U8::from(reached));
+        }
+    }
+
+    /// Mints the next sequential token to `to` with `uri`. Every single-token mint
+    /// path without an explicit id goes through this.
+    fn _mint_one(&mut self, to: Address, uri: &str) -> Result<U256, Vec<u8>> {
+        self._before_mint(to, U256::from(1))?;
+        self._mint_next(to, uri)
+    }
+
+    /// Mints the next sequential token to `to` with `uri`, for callers that already ran
+    /// `_before_mint` for it.
+    fn _mint_next(&mut self, to: Address, uri: &str) -> Result<U256, Vec<u8>> {
+        let token_id = self._next_sequential_id(self.next_id.get());
+
+        self._mint(to, token_id)?;
+        self._set_minted_uri(token_id, uri)?;
+        self._auto_approve(to, token_id)?;
+
+        self.next_id.set(token_id + U256::from(1));
+        self._after_mint(to, token_id, U256::from(1));
+        Ok(token_id)
+    }
+
+    /// Mints the exact `token_id` to `to`, outside of the sequential counter.
+    fn _mint_explicit(&mut self, to: Address, token_id: U256, uri: &str) -> Result<(), Vec<u8>> {
+        if !self._owner_of(token_id).is_zero() || self.burned.get(token_id) {
+            return Err(b"Already minted".to_vec());
+        }
+        self._before_mint(to, U256::from(1))?;
+
+        self._mint(to, token_id)?;
+        self.minted_explicitly.setter(token_id).set(true);
+        self._set_minted_uri(token_id, uri)?;
+        self._auto_approve(to, token_id)?;
+        self._after_mint(to, token_id, U256::from(1));
+        Ok(())
+    }
+
+    /// Builds the on-chain SVG image of a token.
+    fn _svg(&self, token_id: U256) -> String {
+        format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 350 350">"#,
+                r#"<rect width="100%" height="100%" fill="black"/>"#,
+                r#"<text x="50%" y="50%" fill="white" text-anchor="middle">{} #{}</text></svg>"#,
+            ),
+            self.symbol.get_string(),
+            token_id,
+        )
+    }
+
+    /// Returns the on-chain SVG image of a token as a base64 data URI.
+    fn _svg_data_uri(&self, token_id: U256) -> String {
+        format!("data:image/svg+xml;base64,{}", BASE64.encode(self._svg(token_id)))
+    }
+
+    /// Stores the URI of a newly minted token, reverting on a URI that was already
+    /// minted while `enforce_unique_uri` is on.
+    fn _set_minted_uri(&mut self, token_id: U256, uri: &str) -> Result<(), Vec<u8>> {
+        self._check_uri_length(uri.as_bytes())?;
+        if self.enforce_unique_uri.get() && !uri.is_empty() {
+            let hash = keccak(uri.as_bytes());
+            if self.seen_uri_hashes.get(hash) {
+                return Err(b"Duplicate URI".to_vec());
+            }
+            self.seen_uri_hashes.setter(hash).set(true);
+        }
+        self._set_token_uri(token_id, uri);
+        Ok(())
+    }
+
+    /// Reverts if `uri` is longer than `max_uri_length`.
+    fn _check_uri_length(&self, uri: &[u8]) -> Result<(), Vec<u8>> {
+        let max = self.max_uri_length.get();
+        if !max.is_zero() && U256::from(uri.len()) > max {
+            return Err(b"URI too long".to_vec());
+        }
+        Ok(())
+    }
+
+    /// Stores the metadata URI of a token.
+    fn _set_token_uri(&mut self, token_id: U256, uri: &str) {
+        self.token_uris.setter(token_id).set_str(uri);
+    }
+
+    /// Returns the URI stored for a token, falling back to the legacy list and then to
+    /// `base_uri + token_id + uri_suffix` when a base URI is set.
+    fn _stored_token_uri(&self, token_id: U256) -> String {
+        let uri = self.token_uris.get(token_id).get_string();
+        if !uri.is_empty() {
+            return uri;
+        }
+        let uri = self._legacy_token_uri(token_id);
+        if !uri.is_empty() {
+            return uri;
+        }
+        let base_uri = self.base_uri.get_string();
+        if base_uri.is_empty() {
+            return String::new();
+        }
+        format!("{base_uri}{token_id}{}", self.uri_suffix.get_string())
+    }
+
+    /// Looks a token URI up in the legacy newline separated list.
+    fn _legacy_token_uri(&self, token_id: U256) -> String {
+        let full = self.token_uris_str.get_string();
+        let bytes = full.as_bytes();
+        let mut pos = 0;
+        let mut current_token = U256::ZERO;
+        while let Some(offset) = bytes[pos..].iter().position(|&b| b == b'\n') {
+            let start = pos;
+            pos += offset;
+            if current_token == token_id {
+                let uri_bytes = &bytes[start..pos];
+                return String::from_utf8_lossy(uri_bytes).into_owned();
+            }
+            current_token += U256::from(1);
+            pos += 1; // skip \n
+        }
+        // Last one without \n
+        if current_token == token_id && pos < bytes.len() {
+            let uri_bytes = &bytes[pos..];
+            String::from_utf8_lossy(uri_bytes).into_owned()
+        } else {
+            String::new()
+        }
+    }
+}
+
+#[public]
+impl IErc721 for DEMONFT {
+    type Error = Vec<u8>;
+
+    #[selector(name = "balanceOf")]
+    fn balance_of(&self, owner: Address) -> Result<U256, Self::Error> {
+        if owner.is_zero() {
+            return Err(erc721::Error::from(ERC721InvalidOwner { owner: Address::ZERO }).into());
+        }
+        Ok(self._balance(owner))
+    }
+
+    #[selector(name = "ownerOf")]
+    fn owner_of(&self, token_id: U256) -> Result<Address, Self::Error> {
+        self._require_owned(token_id)
+    }
+
+    #[payable]
+    #[selector(name = "safeTransferFrom")]
+    fn safe_transfer_from_with_data(&mut self, from: Address, to: Address, token_id: U256, data: Bytes) -> Result<(), Self::Error> {
+        self.transfer_from(from, to, token_id)?;
+        let operator = self.vm().msg_sender();
+        self.erc721._check_on_erc721_received(operator, from, to, token_id, &data)?;
+        Ok(())
+    }
+
+    #[payable]
+    #[selector(name = "safeTransferFrom")]
+    fn safe_transfer_from(&mut self, from: Address, to: Address, token_id: U256) -> Result<(), Self::Error> {
+        self.safe_transfer_from_with_data(from, to, token_id, vec![].into())
+    }
+
+    #[payable]
+    #[selector(name = "transferFrom")]
+    fn transfer_from(&mut self, from: Address, to: Address, token_id: U256) -> Result<(), Self::Error> {
+        if to.is_zero() {
+            return Err(erc721::Error::from(ERC721InvalidReceiver { receiver: Address::ZERO }).into());
+        }
+        self._check_transfer(from, to, token_id)?;
+        self._check_receiver_allowed(to)?;
+        let fee = self.vm().msg_value();
+        if fee < self.transfer_fee.get() {
+            return Err(b"Insufficient transfer fee".to_vec());
+        }
+        // Nothing would account for ETH left behind in the contract.
+        let treasury = self.treasury.get();
+        if !fee.is_zero() && treasury.is_zero() {
+            return Err(b"No treasury".to_vec());
+        }
+
+        // `_update` with a non-zero `auth` already rejects nonexistent tokens.
+        let previous_owner = self._update(to, token_id, self.vm().msg_sender())?;
+        if previous_owner != from {
+            return Err(erc721::Error::from(ERC721IncorrectOwner { sender: from, token_id, owner: previous_owner }).into());
+        }
+        if !fee.is_zero() {
+            self.vm().transfer_eth(treasury, fee)?;
+        }
+        Ok(())
+    }
+
+    #[selector(name = "approve")]
+    fn approve(&mut self, to: Address, token_id: U256) -> Result<(), Self::Error> {
+        let owner = self._require_owned(token_id)?;
+        let auth = self.vm().msg_sender();
+        if owner != auth && !self.erc721.is_approved_for_all(owner, auth) {
+            return Err(erc721::Error::from(ERC721InvalidApprover { approver: auth }).into());
+        }
+
+        self.erc721._approve(to, token_id, Address::ZERO, false)?;
+        log(self.vm(), Approval { owner, approved: to, token_id });
+        Ok(())
+    }
+
+    #[selector(name = "setApprovalForAll")]
+    fn set_approval_for_all(&mut self, operator: Address, approved: bool) -> Result<(), Self::Error> {
+        self.erc721.set_approval_for_all(operator, approved)?;
+        self._track_operator(self.vm().msg_sender(), operator, approved);
+        Ok(())
+    }
+
+    #[selector(name = "getApproved")]
+    fn get_approved(&self, token_id: U256) -> Result<Address, Self::Error> {
+        self._require_owned(token_id)?;
+        Ok(self.erc721._get_approved(token_id))
+    }
+
+    #[selector(name = "isApprovedForAll")]
+    fn is_approved_for_all(&self, owner: Address, operator: Address) -> bool {
+        self.erc721.is_approved_for_all(owner, operator)
+    }
+}
+
+#[public]
+impl IErc721Metadata for DEMONFT {
+    type Error = Vec<u8>;
+
+    fn name(&self) -> String {
+        self.name.get_string()
+    }
+
+    fn symbol(&self) -> String {
+        self.symbol.get_string()
+    }
+
+    fn token_uri(&self, token_id: U256) -> Result<String, Self::Error> {
+        // Check if token exists
+        self._require_owned(token_id)?;
+
+        let unrevealed_uri = self.unrevealed_uri.get_string();
+        if !unrevealed_uri.is_empty() && !self.is_revealed() {
+            return Ok(unrevealed_uri);
+        }
+
+        match self.metadata_mode() {
+            METADATA_MODE_STORED => {}
+            METADATA_MODE_JSON => {
+                let image = self._svg_data_uri(token_id);
+                let json = format!(r#"{{"name":"{} #{token_id}","image":"{image}"}}"#, self.name.get_string());
+                return Ok(format!("data:application/json;base64,{}", BASE64.encode(json)));
+            }
+            _ => return Ok(self._svg_data_uri(token_id)),
+        }
+
+        // Prefer the external renderer, falling back to the stored URI if it reverts.
+        let renderer = self.metadata_renderer.get();
+        if !renderer.is_zero() {
+            if let Ok(uri) = IMetadataRenderer::new(renderer).token_uri(self, token_id) {
+                return Ok(uri);
+            }
+        }
+
+        let mut uri = self._stored_token_uri(token_id);
+        if self.append_version_query.get() && !uri.is_empty() {
+            // Tag the URI with a short hash of itself, so caches miss once it changes.
+            let separator = if uri.contains('?') { '&' } else { '?' };
+            let version = hex::encode(&keccak(uri.as_bytes())[..4]);
+            uri = format!("{uri}{separator}v={version}");
+        }
+        Ok(uri)
+    }
+}
+
+/// Returns the EIP-712 encoding of a `uint256[]`: the hash of its concatenated items.
+fn hash_token_ids(token_ids: &[U256]) -> FixedBytes<32> {
+    let packed: Vec<u8> = token_ids.iter().flat_map(|id| id.to_be_bytes::<32>()).collect();
+    keccak(packed)
+}
+
+/// Returns true if `proof` shows that `leaf` is in the Merkle tree with `root`, with
+/// each pair of nodes hashed in sorted order.
+fn verify_merkle_proof(proof: &[FixedBytes<32>], root: FixedBytes<32>, leaf: FixedBytes<32>) -> bool {
+    let node = proof.iter().fold(leaf, |node, sibling| {
+        let (a, b) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+        keccak([a.as_slice(), b.as_slice()].concat())
+    });
+    node == root
+}
+
+impl IEip712 for DEMONFT {
+    const NAME: &'static str = "DemoNFT";
+    const VERSION: &'static str = "1";
+
+    /// Lets tests move the contract to another chain. Other builds use the default.
+    #[cfg(test)]
+    #[allow(deprecated)]
+    fn chain_id() -> U256 {
+        test::CHAIN_ID.get().unwrap_or_else(|| U256::from(stylus_sdk::block::chainid()))
+    }
+}
+
+#[public]
+impl IErc165 for DEMONFT {
     fn supports_interface(&self, interface_id: FixedBytes<4>) -> bool {
-        self.erc721.supports_interface(interface_id)
+        DECLARED_INTERFACES.contains(&interface_id.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use motsu::prelude::*;
+    use alloy_sol_types::SolError;
+    use alloy_signer::SignerSync;
+    use openzeppelin_stylus::token::erc20::{Erc20, IErc20 as _};
+    use openzeppelin_stylus::token::erc721::ApprovalForAll;
+
+    thread_local! {
+        /// Chain id reported to EIP-712 in place of the VM's, while set.
+        pub(super) static CHAIN_ID: core::cell::Cell<Option<U256>> = const { core::cell::Cell::new(None) };
+    }
+
+    fn init(contract: &Contract<DEMONFT>, owner: Address, max_supply: u64) {
+        contract.sender(owner).init(
+            "Demo".into(),
+            "DEMO".into(),
+            "ipfs://".into(),
+            U256::from(max_supply),
+            U256::ZERO,
+            Address::ZERO,
+            U256::ZERO,
+        );
+    }
+
+    /// A contract able to receive ETH, since the test VM can't pay plain accounts.
+    #[storage]
+    struct EthReceiver {}
+
+    unsafe impl TopLevelStorage for EthReceiver {}
+
+    #[public]
+    impl EthReceiver {
+        #[receive]
+        fn receive(&mut self) -> Result<(), Vec<u8>> {
+            Ok(())
+        }
+    }
+
+    #[storage]
+    struct MockRenderer {}
+
+    unsafe impl TopLevelStorage for MockRenderer {}
+
+    #[public]
+    impl MockRenderer {
+        #[selector(name = "tokenURI")]
+        fn token_uri(&self, token_id: U256) -> String {
+            format!("render://{token_id}")
+        }
+    }
+
+    #[storage]
+    struct MockValidator {
+        denied: StorageAddress,
+    }
+
+    unsafe impl TopLevelStorage for MockValidator {}
+
+    #[public]
+    impl MockValidator {
+        #[selector(name = "canTransfer")]
+        fn can_transfer(&self, _from: Address, to: Address, _token_id: U256) -> bool {
+            to != self.denied.get()
+        }
+
+        fn deny(&mut self, to: Address) {
+            self.denied.set(to);
+        }
+    }
+
+    #[storage]
+    struct MockOracle {
+        price: StorageU256,
+    }
+
+    unsafe impl TopLevelStorage for MockOracle {}
+
+    #[public]
+    impl MockOracle {
+        #[selector(name = "latestPrice")]
+        fn latest_price(&self) -> U256 {
+            self.price.get()
+        }
+
+        fn set_price(&mut self, price: U256) {
+            self.price.set(price);
+        }
+    }
+
+    #[storage]
+    struct MockRegistry {
+        registrant: StorageAddress,
+        subscription: StorageAddress,
+        blocked: StorageAddress,
+    }
+
+    unsafe impl TopLevelStorage for MockRegistry {}
+
+    #[public]
+    impl MockRegistry {
+        #[selector(name = "registerAndSubscribe")]
+        fn register_and_subscribe(&mut self, registrant: Address, subscription: Address) {
+            self.registrant.set(registrant);
+            self.subscription.set(subscription);
+        }
+
+        #[selector(name = "isOperatorAllowed")]
+        fn is_operator_allowed(&self, registrant: Address, operator: Address) -> bool {
+            registrant != self.registrant.get() || operator != self.blocked.get()
+        }
+
+        fn block(&mut self, operator: Address) {
+            self.blocked.set(operator);
+        }
+    }
+
+    fn uris(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("ipfs://token-{i}")).collect()
+    }
+
+    #[motsu::test]
+    fn test_batch_mint(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+
+        let first_id = contract.sender(alice).batch_mint(alice, uris(10)).unwrap();
+        assert_eq!(U256::ZERO, first_id);
+        assert_eq!(U256::from(10), contract.sender(alice).total_minted());
+        assert_eq!(U256::from(10), contract.sender(alice).balance_of(alice).unwrap());
+        for id in 0..10 {
+            assert_eq!(alice, contract.sender(alice).owner_of(U256::from(id)).unwrap());
+        }
+        assert!(contract.sender(alice).owner_of(U256::from(10)).is_err());
+        assert_eq!("ipfs://token-9", contract.sender(alice).token_uri(U256::from(9)).unwrap());
+
+        // Transferring a token in the middle of the run splits it in two.
+        contract.sender(alice).transfer_from(alice, bob, U256::from(4)).unwrap();
+        for id in 0..10 {
+            let expected = if id == 4 { bob } else { alice };
+            assert_eq!(expected, contract.sender(alice).owner_of(U256::from(id)).unwrap());
+        }
+        assert_eq!(U256::from(9), contract.sender(alice).balance_of(alice).unwrap());
+        assert_eq!(U256::from(1), contract.sender(alice).balance_of(bob).unwrap());
+
+        // The tail of the run keeps resolving after the head moves as well.
+        contract.sender(alice).transfer_from(alice, bob, U256::ZERO).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::ZERO).unwrap());
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(1)).unwrap());
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(3)).unwrap());
+
+        contract.sender(alice).mint(bob, "ipfs://single".into()).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::from(10)).unwrap());
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(9)).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_claim_by_burning(contract: Contract<DEMONFT>, partner: Contract<Erc721>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).set_redemption_collection(partner.address()).unwrap();
+        partner.sender(alice)._mint(bob, U256::from(7)).unwrap();
+        partner.sender(bob).approve(contract.address(), U256::from(7)).unwrap();
+
+        // Only the holder of the partner token can redeem it.
+        let err = contract.sender(alice).claim_by_burning(U256::from(7), "ipfs://claimed".into()).unwrap_err();
+        assert_eq!(b"Not partner token holder".to_vec(), err);
+
+        let token_id = contract.sender(bob).claim_by_burning(U256::from(7), "ipfs://claimed".into()).unwrap();
+        assert_eq!(bob, contract.sender(bob).owner_of(token_id).unwrap());
+        assert_eq!(BURN_ADDRESS, partner.sender(bob).owner_of(U256::from(7)).unwrap());
+        assert_eq!("ipfs://claimed", contract.sender(bob).token_uri(token_id).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_withdraw_events(contract: Contract<DEMONFT>, token: Contract<Erc20>, owner: Contract<EthReceiver>, treasury: Contract<EthReceiver>, bob: Address) {
+        init(&contract, owner.address(), 0);
+
+        contract.fund(U256::from(300));
+        assert!(contract.sender(bob).withdraw().is_err());
+        contract.sender(owner.address()).withdraw().unwrap();
+        contract.assert_emitted(&EthWithdrawn { to: owner.address(), amount: U256::from(300) });
+        assert_eq!(U256::from(300), owner.balance());
+
+        contract.fund(U256::from(50));
+        contract.sender(owner.address()).withdraw_to(treasury.address()).unwrap();
+        contract.assert_emitted(&EthWithdrawn { to: treasury.address(), amount: U256::from(50) });
+        assert_eq!(U256::from(50), treasury.balance());
+        assert_eq!(U256::ZERO, contract.balance());
+
+        token.sender(owner.address())._mint(contract.address(), U256::from(1_000)).unwrap();
+        contract.sender(owner.address()).withdraw_erc20(token.address(), bob).unwrap();
+        contract.assert_emitted(&Erc20Withdrawn { token: token.address(), to: bob, amount: U256::from(1_000) });
+        assert_eq!(U256::from(1_000), token.sender(bob).balance_of(bob));
+    }
+
+    #[motsu::test]
+    fn test_withdraw_amount(contract: Contract<DEMONFT>, treasury: Contract<EthReceiver>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.fund(U256::from(300));
+        let err = contract.sender(bob).withdraw_amount(treasury.address(), U256::from(100)).unwrap_err();
+        assert_eq!(b"Not owner".to_vec(), err);
+
+        contract.sender(alice).withdraw_amount(treasury.address(), U256::from(100)).unwrap();
+        contract.assert_emitted(&EthWithdrawn { to: treasury.address(), amount: U256::from(100) });
+        assert_eq!(U256::from(100), treasury.balance());
+        assert_eq!(U256::from(200), contract.balance());
+
+        let err = contract.sender(alice).withdraw_amount(treasury.address(), U256::from(201)).unwrap_err();
+        assert_eq!(b"Insufficient balance".to_vec(), err);
+        let err = contract.sender(alice).withdraw_amount(Address::ZERO, U256::from(100)).unwrap_err();
+        assert_eq!(b"Zero address".to_vec(), err);
+        assert_eq!(U256::from(200), contract.balance());
+    }
+
+    #[motsu::test]
+    fn test_multisig_withdraw(
+        contract: Contract<DEMONFT>,
+        treasury: Contract<EthReceiver>,
+        alice: Address,
+        bob: Address,
+        carol: Address,
+        dave: Address,
+    ) {
+        init(&contract, alice, 0);
+        assert_eq!(b"Bad threshold".to_vec(), contract.sender(alice).set_multisig(vec![bob, carol], U256::from(3)).unwrap_err());
+        contract.sender(alice).set_multisig(vec![alice, bob, carol], U256::from(2)).unwrap();
+        assert_eq!((vec![alice, bob, carol], U256::from(2)), contract.sender(alice).multisig());
+        contract.fund(U256::from(300));
+        assert_eq!(b"Multisig required".to_vec(), contract.sender(alice).withdraw_to(treasury.address()).unwrap_err());
+        assert_eq!(b"Not signer".to_vec(), contract.sender(dave).propose_action(ACTION_WITHDRAW, dave).unwrap_err());
+
+        let action = contract.sender(bob).propose_action(ACTION_WITHDRAW, treasury.address()).unwrap();
+        assert_eq!(b"Already confirmed".to_vec(), contract.sender(bob).confirm_action(action).unwrap_err());
+        assert_eq!(b"Not enough confirmations".to_vec(), contract.sender(bob).execute_action(action).unwrap_err());
+
+        contract.sender(carol).confirm_action(action).unwrap();
+        assert_eq!((U256::from(2), false), contract.sender(alice).action_status(action));
+        contract.sender(alice).execute_action(action).unwrap();
+        assert_eq!(U256::from(300), treasury.balance());
+        assert_eq!((U256::from(2), true), contract.sender(alice).action_status(action));
+        assert_eq!(b"Unknown action".to_vec(), contract.sender(alice).execute_action(action).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_max_supply_timelock(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 10);
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        assert_eq!(b"No proposal".to_vec(), contract.sender(alice).apply_max_supply().unwrap_err());
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).propose_max_supply(U256::from(5)).unwrap_err());
+        assert_eq!(b"Below minted".to_vec(), contract.sender(alice).propose_max_supply(U256::from(2)).unwrap_err());
+
+        contract.sender(alice).propose_max_supply(U256::from(5)).unwrap();
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+        let unlock_at = now + U256::from(MAX_SUPPLY_TIMELOCK);
+        assert_eq!((U256::from(5), unlock_at), contract.sender(alice).pending_max_supply());
+        assert_eq!(b"Timelock active".to_vec(), contract.sender(alice).apply_max_supply().unwrap_err());
+        assert_eq!(U256::from(10), contract.sender(alice).max_supply());
+
+        // Pretend the timelock has passed.
+        contract.sender(alice).max_supply_unlock_at.set(now);
+        contract.sender(alice).apply_max_supply().unwrap();
+        assert_eq!(U256::from(5), contract.sender(alice).max_supply());
+        assert_eq!((U256::ZERO, U256::ZERO), contract.sender(alice).pending_max_supply());
+        assert!(contract.sender(alice).batch_mint(alice, uris(3)).is_err());
+    }
+
+    #[motsu::test]
+    fn test_max_supply_error(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 2);
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+
+        let err = contract.sender(alice).mint(alice, "ipfs://over".into()).unwrap_err();
+        let decoded = MaxSupplyReached::abi_decode(&err, true).unwrap();
+        assert_eq!(U256::from(2), decoded.minted);
+        assert_eq!(U256::from(2), decoded.cap);
+
+        let err = contract.sender(alice).batch_mint(alice, uris(1)).unwrap_err();
+        assert!(MaxSupplyReached::abi_decode(&err, true).is_ok());
+    }
+
+    #[motsu::test]
+    fn test_can_transfer(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address, dave: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+        let token_id = U256::ZERO;
+
+        assert!(contract.sender(alice).can_transfer(alice, token_id));
+        assert!(!contract.sender(alice).can_transfer(bob, token_id));
+
+        contract.sender(alice).approve(bob, token_id).unwrap();
+        assert!(contract.sender(alice).can_transfer(bob, token_id));
+        assert!(!contract.sender(alice).can_transfer(bob, U256::from(1)));
+
+        contract.sender(alice).set_approval_for_all(carol, true).unwrap();
+        assert!(contract.sender(alice).can_transfer(carol, U256::from(1)));
+
+        assert!(!contract.sender(alice).can_transfer(dave, token_id));
+        assert!(!contract.sender(alice).can_transfer(alice, U256::from(2)));
+    }
+
+    #[motsu::test]
+    fn test_staking(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+        let token_id = U256::from(1);
+
+        contract.sender(alice).stake(token_id).unwrap();
+        assert_eq!(contract.address(), contract.sender(alice).owner_of(token_id).unwrap());
+        assert_eq!(alice, contract.sender(alice).staker_of(token_id));
+        assert_eq!(U256::ZERO, contract.sender(alice).staked_duration(token_id));
+        assert_eq!(b"Already staked".to_vec(), contract.sender(alice).stake(token_id).unwrap_err());
+
+        // The test VM clock doesn't advance, so move the stake start back instead.
+        let staked_at = contract.sender(alice).stakes.get(token_id).staked_at.get();
+        contract.sender(alice).stakes.setter(token_id).staked_at.set(staked_at - U256::from(3_600));
+        assert_eq!(U256::from(3_600), contract.sender(alice).staked_duration(token_id));
+
+        assert_eq!(b"Not staker".to_vec(), contract.sender(bob).unstake(token_id).unwrap_err());
+
+        contract.sender(alice).unstake(token_id).unwrap();
+        assert_eq!(alice, contract.sender(alice).owner_of(token_id).unwrap());
+        assert_eq!(Address::ZERO, contract.sender(alice).staker_of(token_id));
+        assert_eq!(U256::ZERO, contract.sender(alice).staked_duration(token_id));
+        assert_eq!(b"Not staked".to_vec(), contract.sender(alice).unstake(token_id).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_claim_rewards(contract: Contract<DEMONFT>, reward: Contract<Erc20>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).set_staking_rewards(reward.address(), U256::from(5)).unwrap();
+        reward.sender(alice)._mint(contract.address(), U256::from(1_000_000)).unwrap();
+        contract.sender(alice).mint(bob, "ipfs://staked".into()).unwrap();
+        let token_id = U256::ZERO;
+
+        contract.sender(bob).stake(token_id).unwrap();
+        let rewards_from = contract.sender(bob).stakes.get(token_id).rewards_from.get();
+        contract.sender(bob).stakes.setter(token_id).rewards_from.set(rewards_from - U256::from(100));
+        assert_eq!(U256::from(500), contract.sender(bob).pending_rewards(token_id));
+
+        assert_eq!(b"Not staker".to_vec(), contract.sender(alice).claim_rewards(token_id).unwrap_err());
+
+        assert_eq!(U256::from(500), contract.sender(bob).claim_rewards(token_id).unwrap());
+        assert_eq!(U256::from(500), reward.sender(bob).balance_of(bob));
+        assert_eq!(U256::ZERO, contract.sender(bob).pending_rewards(token_id));
+        assert_eq!(U256::ZERO, contract.sender(bob).claim_rewards(token_id).unwrap());
+        assert_eq!(U256::from(500), reward.sender(bob).balance_of(bob));
+    }
+
+    #[motsu::test]
+    fn test_unstake_claims_rewards(contract: Contract<DEMONFT>, reward: Contract<Erc20>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).set_staking_rewards(reward.address(), U256::from(5)).unwrap();
+        reward.sender(alice)._mint(contract.address(), U256::from(1_000_000)).unwrap();
+        contract.sender(alice).mint(bob, "ipfs://staked".into()).unwrap();
+        let token_id = U256::ZERO;
+
+        contract.sender(bob).stake(token_id).unwrap();
+        let rewards_from = contract.sender(bob).stakes.get(token_id).rewards_from.get();
+        contract.sender(bob).stakes.setter(token_id).rewards_from.set(rewards_from - U256::from(100));
+
+        contract.sender(bob).unstake(token_id).unwrap();
+        assert_eq!(U256::from(500), reward.sender(bob).balance_of(bob));
+        assert_eq!(bob, contract.sender(bob).owner_of(token_id).unwrap());
+        assert_eq!(U256::ZERO, contract.sender(bob).pending_rewards(token_id));
+    }
+
+    #[motsu::test]
+    fn test_set_metadata_renderer(contract: Contract<DEMONFT>, renderer: Contract<MockRenderer>, alice: Address) {
+        init(&contract, alice, 0);
+
+        // Nothing is minted yet, so there is nothing to refresh.
+        contract.sender(alice).set_metadata_renderer(renderer.address()).unwrap();
+        assert!(contract.all_events().is_empty());
+
+        contract.sender(alice).set_metadata_renderer(Address::ZERO).unwrap();
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        assert_eq!("ipfs://token-1", contract.sender(alice).token_uri(U256::from(1)).unwrap());
+
+        contract.sender(alice).set_metadata_renderer(renderer.address()).unwrap();
+        contract.assert_emitted(&BatchMetadataUpdate { _fromTokenId: U256::ZERO, _toTokenId: U256::from(2) });
+        assert_eq!("render://1", contract.sender(alice).token_uri(U256::from(1)).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_buy(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        let price = U256::from(100);
+        bob.fund(U256::from(1000));
+
+        assert_eq!(b"Not for sale".to_vec(), contract.sender_and_value(bob, price).buy(U256::from(1), "ipfs://one".into()).unwrap_err());
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_token_price(U256::from(1), price).unwrap_err());
+        contract.sender(alice).set_token_price(U256::from(1), price).unwrap();
+        assert_eq!(price, contract.sender(alice).token_price(U256::from(1)));
+
+        let err = contract.sender_and_value(bob, U256::from(99)).buy(U256::from(1), "ipfs://one".into()).unwrap_err();
+        assert_eq!(b"Insufficient payment".to_vec(), err);
+
+        contract.sender_and_value(bob, price).buy(U256::from(1), "ipfs://one".into()).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::from(1)).unwrap());
+        assert_eq!("ipfs://one", contract.sender(alice).token_uri(U256::from(1)).unwrap());
+        assert_eq!(U256::from(1), contract.sender(alice).total_minted());
+
+        let err = contract.sender_and_value(bob, price).buy(U256::from(1), "ipfs://again".into()).unwrap_err();
+        assert_eq!(b"Already sold".to_vec(), err);
+        assert_eq!(b"Already minted".to_vec(), contract.sender(alice).set_token_price(U256::from(1), price).unwrap_err());
+
+        // Sequential minting skips the sold id.
+        assert_eq!(U256::ZERO, contract.sender(alice).mint(alice, "ipfs://zero".into()).unwrap());
+        assert_eq!(U256::from(2), contract.sender(alice).batch_mint(alice, uris(2)).unwrap());
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(3)).unwrap());
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::from(1)).unwrap());
+        assert_eq!(U256::from(4), contract.sender(alice).total_minted());
+    }
+
+    #[motsu::test]
+    fn test_batch_mint_skips_sold_ids(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(10));
+        contract.sender(alice).set_token_price(U256::from(2), U256::from(10)).unwrap();
+        contract.sender_and_value(bob, U256::from(10)).buy(U256::from(2), "ipfs://sold".into()).unwrap();
+
+        assert_eq!(U256::ZERO, contract.sender(alice).batch_mint(alice, uris(4)).unwrap());
+        for (id, expected) in [(0, alice), (1, alice), (2, bob), (3, alice), (4, alice)] {
+            assert_eq!(expected, contract.sender(alice).owner_of(U256::from(id)).unwrap());
+        }
+        assert_eq!("ipfs://token-2", contract.sender(alice).token_uri(U256::from(3)).unwrap());
+        assert_eq!(U256::from(4), contract.sender(alice).balance_of(alice).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_sequential_mint_skips_listed_ids(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(10));
+        contract.sender(alice).set_token_price(U256::from(1), U256::from(10)).unwrap();
+        contract.sender(alice).set_token_price(U256::from(3), U256::from(10)).unwrap();
+        assert_eq!(U256::ZERO, contract.sender(alice).next_token_id());
+
+        assert_eq!(U256::ZERO, contract.sender(bob).mint(bob, "ipfs://zero".into()).unwrap());
+        assert_eq!(U256::from(2), contract.sender(bob).batch_mint(bob, uris(2)).unwrap());
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::from(4)).unwrap());
+        for id in [1, 3] {
+            assert!(contract.sender(alice).owner_of(U256::from(id)).is_err());
+        }
+
+        // The listed ids are still for sale.
+        contract.sender_and_value(bob, U256::from(10)).buy(U256::from(3), "ipfs://three".into()).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::from(3)).unwrap());
+
+        // A delisted id goes back to sequential minting.
+        contract.sender(alice).set_token_price(U256::from(6), U256::from(10)).unwrap();
+        contract.sender(alice).set_token_price(U256::from(6), U256::ZERO).unwrap();
+        assert_eq!(U256::from(5), contract.sender(alice).batch_mint(alice, uris(2)).unwrap());
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(6)).unwrap());
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::from(4)).unwrap());
+        assert!(contract.sender(alice).owner_of(U256::from(1)).is_err());
+    }
+
+    #[motsu::test]
+    fn test_max_batch_size(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(U256::from(DEFAULT_MAX_BATCH_SIZE), contract.sender(alice).max_batch_size());
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_max_batch_size(U256::from(3)).unwrap_err());
+        contract.sender(alice).set_max_batch_size(U256::from(3)).unwrap();
+
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        assert_eq!(b"Batch too large".to_vec(), contract.sender(alice).batch_mint(alice, uris(4)).unwrap_err());
+        assert_eq!(U256::from(3), contract.sender(alice).total_minted());
+
+        let ids = |n: u64| (0..n).map(U256::from).collect::<Vec<_>>();
+        assert_eq!(vec![alice; 3], contract.sender(alice).owners_of(ids(3)).unwrap());
+        assert_eq!(b"Batch too large".to_vec(), contract.sender(alice).owners_of(ids(4)).unwrap_err());
+        assert_eq!(3, contract.sender(alice).uris_of(ids(3)).unwrap().len());
+        assert_eq!(b"Batch too large".to_vec(), contract.sender(alice).uris_of(ids(4)).unwrap_err());
+        assert_eq!(b"Batch too large".to_vec(), contract.sender(alice).batch_burn(ids(4)).unwrap_err());
+        assert_eq!(U256::from(3), contract.sender(alice).balance_of(alice).unwrap());
+        contract.sender(alice).batch_burn(ids(3)).unwrap();
+        assert_eq!(U256::ZERO, contract.sender(alice).balance_of(alice).unwrap());
+
+        contract.sender(alice).set_max_batch_size(U256::ZERO).unwrap();
+        contract.sender(alice).batch_mint(alice, uris(4)).unwrap();
+    }
+
+    #[motsu::test]
+    fn test_supports_interface(contract: Contract<DEMONFT>, alice: Address) {
+        for id in [0x80ac58cd_u32, 0x5b5e139f, 0x49064906, 0x2a55205a, 0x01ffc9a7, 0x150b7a02] {
+            assert!(contract.sender(alice).supports_interface(id.to_be_bytes().into()), "{id:#x}");
+        }
+        assert!(!contract.sender(alice).supports_interface(0xdeadbeef_u32.to_be_bytes().into()));
+    }
+
+    #[motsu::test]
+    fn test_receives_safe_transfers(contract: Contract<DEMONFT>, collection: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        init(&collection, alice, 0);
+        collection.sender(alice).batch_mint(alice, uris(1)).unwrap();
+
+        collection.sender(alice).safe_transfer_from(alice, contract.address(), U256::ZERO).unwrap();
+        assert_eq!(contract.address(), collection.sender(alice).owner_of(U256::ZERO).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_declared_interfaces(contract: Contract<DEMONFT>, alice: Address) {
+        let declared = contract.sender(alice).declared_interfaces();
+        for id in [0x80ac58cd_u32, 0x5b5e139f, 0x01ffc9a7] {
+            assert!(declared.contains(&id.to_be_bytes().into()), "{id:#x}");
+        }
+        for id in declared {
+            assert!(contract.sender(alice).supports_interface(id), "{id}");
+        }
+    }
+
+    #[motsu::test]
+    fn test_emit_metadata_updates(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).emit_metadata_update(U256::from(7)).unwrap_err());
+        contract.sender(alice).emit_metadata_update(U256::from(7)).unwrap();
+        contract.assert_emitted(&MetadataUpdate { _tokenId: U256::from(7) });
+
+        contract.sender(alice).emit_batch_metadata_update(U256::from(3), U256::from(9)).unwrap();
+        contract.assert_emitted(&BatchMetadataUpdate { _fromTokenId: U256::from(3), _toTokenId: U256::from(9) });
+        contract.sender(alice).emit_batch_metadata_update(U256::from(4), U256::from(4)).unwrap();
+        contract.assert_emitted(&BatchMetadataUpdate { _fromTokenId: U256::from(4), _toTokenId: U256::from(4) });
+        let err = contract.sender(alice).emit_batch_metadata_update(U256::from(9), U256::from(3)).unwrap_err();
+        assert_eq!(b"Invalid range".to_vec(), err);
+        let err = contract.sender(bob).emit_batch_metadata_update(U256::ZERO, U256::from(1)).unwrap_err();
+        assert_eq!(b"Not owner".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_reveal_and_freeze(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, vec![String::new(); 2]).unwrap();
+        contract.sender(alice).set_unrevealed_uri("ipfs://hidden".into()).unwrap();
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).reveal_and_freeze("ipfs://real/".into()).unwrap_err());
+        contract.sender(alice).set_max_uri_length(U256::from(8)).unwrap();
+        assert_eq!(b"URI too long".to_vec(), contract.sender(alice).reveal_and_freeze("ipfs://real/".into()).unwrap_err());
+        assert!(!contract.sender(alice).base_uri_frozen());
+        contract.sender(alice).set_max_uri_length(U256::from(64)).unwrap();
+
+        contract.sender(alice).reveal_and_freeze("ipfs://real/".into()).unwrap();
+        contract.assert_emitted(&Revealed { base_uri: "ipfs://real/".into() });
+        contract.assert_emitted(&BatchMetadataUpdate { _fromTokenId: U256::ZERO, _toTokenId: U256::from(1) });
+        assert!(contract.sender(alice).is_revealed());
+        assert!(contract.sender(alice).base_uri_frozen());
+        assert_eq!("ipfs://real/1", contract.sender(alice).token_uri(U256::from(1)).unwrap());
+
+        assert_eq!(b"Base URI frozen".to_vec(), contract.sender(alice).set_base_uri("ipfs://other/".into()).unwrap_err());
+        let err = contract.sender(alice).reveal_and_freeze("ipfs://other/".into()).unwrap_err();
+        assert_eq!(b"Already revealed".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_scheduled_reveal(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).mint(alice, "ipfs://real".into()).unwrap();
+        contract.sender(alice).set_unrevealed_uri("ipfs://hidden".into()).unwrap();
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+
+        // Nothing scheduled yet.
+        assert!(!contract.sender(alice).is_revealed());
+        assert_eq!("ipfs://hidden", contract.sender(alice).token_uri(U256::ZERO).unwrap());
+
+        let err = contract.sender(alice).schedule_reveal(now).unwrap_err();
+        assert_eq!(b"Reveal time passed".to_vec(), err);
+        contract.sender(alice).schedule_reveal(now + U256::from(100)).unwrap();
+        assert!(!contract.sender(alice).is_revealed());
+
+        // Cancelling keeps the collection hidden.
+        contract.sender(alice).cancel_reveal().unwrap();
+        assert_eq!(U256::ZERO, contract.sender(alice).reveal_at());
+        assert!(!contract.sender(alice).is_revealed());
+
+        // Once the scheduled time passes the real URI shows.
+        contract.sender(alice).schedule_reveal(now + U256::from(100)).unwrap();
+        contract.sender(alice).reveal_at.set(now);
+        assert!(contract.sender(alice).is_revealed());
+        assert_eq!("ipfs://real", contract.sender(alice).token_uri(U256::ZERO).unwrap());
+        assert_eq!(b"Already revealed".to_vec(), contract.sender(alice).cancel_reveal().unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_manual_reveal_overrides_schedule(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).mint(alice, "ipfs://real".into()).unwrap();
+        contract.sender(alice).set_unrevealed_uri("ipfs://hidden".into()).unwrap();
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+        contract.sender(alice).schedule_reveal(now + U256::from(100)).unwrap();
+
+        contract.sender(alice).reveal().unwrap();
+        contract.assert_emitted(&BatchMetadataUpdate { _fromTokenId: U256::ZERO, _toTokenId: U256::ZERO });
+        assert!(contract.sender(alice).is_revealed());
+        assert_eq!("ipfs://real", contract.sender(alice).token_uri(U256::ZERO).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_mint_with_credit(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).grant_credits(bob, U256::from(2)).unwrap_err());
+        contract.sender(alice).grant_credits(bob, U256::from(2)).unwrap();
+        assert_eq!(U256::from(2), contract.sender(alice).credits_of(bob));
+
+        assert_eq!(U256::ZERO, contract.sender(bob).mint_with_credit("ipfs://a".into()).unwrap());
+        assert_eq!(U256::from(1), contract.sender(bob).mint_with_credit("ipfs://b".into()).unwrap());
+        assert_eq!(U256::ZERO, contract.sender(alice).credits_of(bob));
+        assert_eq!(U256::from(2), contract.sender(alice).balance_of(bob).unwrap());
+
+        assert_eq!(b"No credits".to_vec(), contract.sender(bob).mint_with_credit("ipfs://c".into()).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_storage_version(contract: Contract<DEMONFT>, alice: Address) {
+        assert_eq!(0, contract.sender(alice).storage_version());
+        init(&contract, alice, 0);
+        assert_eq!(STORAGE_VERSION, contract.sender(alice).storage_version());
+
+        let err = contract.sender(alice).set_storage_version(STORAGE_VERSION).unwrap_err();
+        assert_eq!(b"Invalid storage version".to_vec(), err);
+        let err = contract.sender(alice).set_storage_version(STORAGE_VERSION + 1).unwrap_err();
+        assert_eq!(b"Invalid storage version".to_vec(), err);
+
+        // A deployment from before versioning can be brought up to date.
+        contract.sender(alice).storage_version.set(U256::ZERO);
+        contract.sender(alice).set_storage_version(STORAGE_VERSION).unwrap();
+        assert_eq!(STORAGE_VERSION, contract.sender(alice).storage_version());
+    }
+
+    #[motsu::test]
+    fn test_drain(contract: Contract<DEMONFT>, token: Contract<Erc20>, owner: Contract<EthReceiver>, treasury: Contract<EthReceiver>, bob: Address) {
+        init(&contract, owner.address(), 0);
+        contract.fund(U256::from(300));
+        token.sender(owner.address())._mint(contract.address(), U256::from(1_000)).unwrap();
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_fee_token(token.address()).unwrap_err());
+        contract.sender(owner.address()).set_fee_token(token.address()).unwrap();
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).drain(treasury.address()).unwrap_err());
+        assert_eq!(b"Zero address".to_vec(), contract.sender(owner.address()).drain(Address::ZERO).unwrap_err());
+
+        contract.sender(owner.address()).drain(treasury.address()).unwrap();
+        assert_eq!(U256::from(300), treasury.balance());
+        assert_eq!(U256::ZERO, contract.balance());
+        assert_eq!(U256::from(1_000), token.sender(bob).balance_of(treasury.address()));
+        assert_eq!(U256::ZERO, token.sender(bob).balance_of(contract.address()));
+    }
+
+    #[motsu::test]
+    fn test_burn_unminted_reserved(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 10);
+        assert_eq!(b"Nothing minted".to_vec(), contract.sender(alice).burn_unminted_reserved().unwrap_err());
+        contract.sender(alice).set_reserved(U256::from(3)).unwrap();
+        contract.sender(alice).batch_mint(alice, uris(4)).unwrap();
+        contract.sender(alice).mint_reserved(bob, String::new()).unwrap();
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).burn_unminted_reserved().unwrap_err());
+
+        contract.sender(alice).burn_unminted_reserved().unwrap();
+        contract.assert_emitted(&CollectionFinalized { totalSupply: U256::from(5) });
+        assert_eq!(contract.sender(alice).total_minted(), contract.sender(alice).max_supply());
+        assert_eq!(U256::ZERO, contract.sender(alice).reserved_remaining());
+        assert_eq!(b"No reserve left".to_vec(), contract.sender(alice).mint_reserved(bob, String::new()).unwrap_err());
+        assert!(contract.sender(alice).mint(bob, String::new()).is_err());
+    }
+
+    #[motsu::test]
+    fn test_multisig_guards_funds_and_metadata(
+        contract: Contract<DEMONFT>,
+        token: Contract<Erc20>,
+        treasury: Contract<EthReceiver>,
+        alice: Address,
+        bob: Address,
+        carol: Address,
+    ) {
+        init(&contract, alice, 0);
+        contract.fund(U256::from(300));
+        token.sender(alice)._mint(contract.address(), U256::from(1_000)).unwrap();
+        contract.sender(alice).set_fee_token(token.address()).unwrap();
+        contract.sender(alice).set_multisig(vec![bob, carol], U256::from(2)).unwrap();
+
+        let required = b"Multisig required".to_vec();
+        assert_eq!(required, contract.sender(alice).reveal_and_freeze("ipfs://real/".into()).unwrap_err());
+        assert!(!contract.sender(alice).base_uri_frozen());
+        assert_eq!(required, contract.sender(alice).withdraw_erc20(token.address(), alice).unwrap_err());
+        assert_eq!(required, contract.sender(alice).drain(treasury.address()).unwrap_err());
+        assert_eq!(U256::from(1_000), token.sender(bob).balance_of(contract.address()));
+
+        let action = contract.sender(bob).propose_action(ACTION_DRAIN, treasury.address()).unwrap();
+        contract.sender(carol).confirm_action(action).unwrap();
+        contract.sender(bob).execute_action(action).unwrap();
+        assert_eq!(U256::from(300), treasury.balance());
+        assert_eq!(U256::from(1_000), token.sender(bob).balance_of(treasury.address()));
+
+        token.sender(alice)._mint(contract.address(), U256::from(500)).unwrap();
+        let action = contract.sender(bob).propose_erc20_withdrawal(token.address(), carol).unwrap();
+        contract.sender(carol).confirm_action(action).unwrap();
+        contract.sender(carol).execute_action(action).unwrap();
+        assert_eq!(U256::from(500), token.sender(bob).balance_of(carol));
+
+        let action = contract.sender(carol).propose_reveal_and_freeze("ipfs://real/".into()).unwrap();
+        contract.sender(bob).confirm_action(action).unwrap();
+        contract.sender(bob).execute_action(action).unwrap();
+        assert!(contract.sender(alice).is_revealed());
+        assert!(contract.sender(alice).base_uri_frozen());
+        assert_eq!(b"Not signer".to_vec(), contract.sender(alice).propose_reveal_and_freeze("ipfs://x/".into()).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_multisig_burn_unminted_reserved(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 10);
+        contract.sender(alice).set_reserved(U256::from(3)).unwrap();
+        contract.sender(alice).mint(alice, String::new()).unwrap();
+        contract.sender(alice).set_multisig(vec![bob, carol], U256::from(2)).unwrap();
+        assert_eq!(b"Multisig required".to_vec(), contract.sender(alice).burn_unminted_reserved().unwrap_err());
+        assert_eq!(U256::from(10), contract.sender(alice).max_supply());
+
+        let action = contract.sender(bob).propose_action(ACTION_FINALIZE_SUPPLY, Address::ZERO).unwrap();
+        contract.sender(carol).confirm_action(action).unwrap();
+        contract.sender(carol).execute_action(action).unwrap();
+        contract.assert_emitted(&CollectionFinalized { totalSupply: U256::from(1) });
+        assert_eq!(U256::from(1), contract.sender(alice).max_supply());
+        assert_eq!(U256::ZERO, contract.sender(alice).reserved_remaining());
+        assert_eq!(b"Unknown action".to_vec(), contract.sender(bob).propose_action(ACTION_DRAIN + 1, Address::ZERO).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_reserved_supply(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 5);
+        assert_eq!(b"Reserve exceeds supply".to_vec(), contract.sender(alice).set_reserved(U256::from(6)).unwrap_err());
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_reserved(U256::from(2)).unwrap_err());
+        contract.sender(alice).set_reserved(U256::from(2)).unwrap();
+
+        // Public minting stops with exactly the reserve left.
+        contract.sender(bob).batch_mint(bob, uris(2)).unwrap();
+        contract.sender(bob).mint(bob, "ipfs://public".into()).unwrap();
+        let err = contract.sender(bob).mint(bob, "ipfs://over".into()).unwrap_err();
+        let decoded = MaxSupplyReached::abi_decode(&err, true).unwrap();
+        assert_eq!(U256::from(3), decoded.minted);
+        assert_eq!(U256::from(3), decoded.cap);
+        assert!(contract.sender(bob).batch_mint(bob, uris(1)).is_err());
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).mint_reserved(bob, "ipfs://team".into()).unwrap_err());
+        contract.sender(alice).mint_reserved(alice, "ipfs://team-0".into()).unwrap();
+        contract.sender(alice).mint_reserved(alice, "ipfs://team-1".into()).unwrap();
+        assert_eq!(U256::ZERO, contract.sender(alice).reserved_remaining());
+        assert_eq!(U256::from(5), contract.sender(alice).total_minted());
+        assert_eq!(b"No reserve left".to_vec(), contract.sender(alice).mint_reserved(alice, "ipfs://x".into()).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_append_version_query(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).mint(alice, "ipfs://plain".into()).unwrap();
+        contract.sender(alice).mint(alice, "https://example.com/1?size=large".into()).unwrap();
+        assert_eq!("ipfs://plain", contract.sender(alice).token_uri(U256::ZERO).unwrap());
+
+        contract.sender(alice).set_append_version_query(true).unwrap();
+        let plain = hex::encode(&keccak("ipfs://plain")[..4]);
+        assert_eq!(format!("ipfs://plain?v={plain}"), contract.sender(alice).token_uri(U256::ZERO).unwrap());
+        let query = hex::encode(&keccak("https://example.com/1?size=large")[..4]);
+        assert_eq!(
+            format!("https://example.com/1?size=large&v={query}"),
+            contract.sender(alice).token_uri(U256::from(1)).unwrap()
+        );
+    }
+
+    #[motsu::test]
+    fn test_free_mint(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(b"No free mints".to_vec(), contract.sender(bob).free_mint("ipfs://a".into()).unwrap_err());
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_free_allocation(bob, U256::from(1)).unwrap_err());
+        contract.sender(alice).set_free_allocation(bob, U256::from(1)).unwrap();
+        assert_eq!(U256::from(1), contract.sender(alice).free_allocation(bob));
+
+        let token_id = contract.sender(bob).free_mint("ipfs://a".into()).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(token_id).unwrap());
+        assert_eq!(U256::ZERO, contract.sender(alice).free_allocation(bob));
+        assert_eq!(b"No free mints".to_vec(), contract.sender(bob).free_mint("ipfs://b".into()).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_public_mint(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(1000));
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_public_price(U256::from(100)).unwrap_err());
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+
+        let err = contract.sender_and_value(bob, U256::from(99)).public_mint("ipfs://a".into()).unwrap_err();
+        assert_eq!(b"Insufficient payment".to_vec(), err);
+        let token_id = contract.sender_and_value(bob, U256::from(100)).public_mint("ipfs://a".into()).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(token_id).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_dutch_auction(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(1000));
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+        let (start_price, end_price, duration) = (U256::from(1000), U256::from(200), U256::from(100));
+        let err = contract.sender(alice).set_dutch_auction(end_price, start_price, now, duration).unwrap_err();
+        assert_eq!(b"Invalid auction bounds".to_vec(), err);
+
+        // Halfway through, the price is halfway down.
+        contract.sender(alice).set_dutch_auction(start_price, end_price, now - U256::from(50), duration).unwrap();
+        assert_eq!(U256::from(600), contract.sender(bob).current_price());
+        let token_id = contract.sender_and_value(bob, U256::from(600)).dutch_auction_mint("ipfs://a".into()).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(token_id).unwrap());
+
+        // Long after it ends, the price stays at the floor.
+        contract.sender(alice).set_dutch_auction(start_price, end_price, now - U256::from(1_000), duration).unwrap();
+        assert_eq!(end_price, contract.sender(bob).current_price());
+        contract.sender(alice).set_dutch_auction(start_price, end_price, now + U256::from(10), duration).unwrap();
+        assert_eq!(start_price, contract.sender(bob).current_price());
+        let err = contract.sender_and_value(bob, U256::from(400)).dutch_auction_mint("ipfs://b".into()).unwrap_err();
+        assert_eq!(b"Auction not started".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_allowlist_claimed(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(1_000));
+        let tier_id = U256::from(1);
+        contract.sender(alice).set_tier(tier_id, keccak(bob), U256::from(10), U256::from(3)).unwrap();
+        contract.sender(alice).set_allowlisted(bob, true).unwrap();
+        assert_eq!(U256::from(3), contract.sender(bob).tier_remaining(tier_id, bob, vec![]));
+
+        contract.sender_and_value(bob, U256::from(10)).allowlist_mint_tier(tier_id, vec![], U256::from(1)).unwrap();
+        contract.sender(bob).allowlist_mint("ipfs://a".into()).unwrap();
+        assert_eq!(U256::from(2), contract.sender(bob).allowlist_claimed(bob));
+        assert_eq!(U256::from(2), contract.sender(bob).tier_remaining(tier_id, bob, vec![]));
+
+        assert_eq!(U256::ZERO, contract.sender(bob).allowlist_claimed(carol));
+        assert_eq!(U256::ZERO, contract.sender(bob).tier_remaining(tier_id, carol, vec![]));
+        contract.sender(alice).set_tier(tier_id, keccak(bob), U256::from(10), U256::ZERO).unwrap();
+        assert_eq!(U256::MAX, contract.sender(bob).tier_remaining(tier_id, bob, vec![]));
+    }
+
+    #[motsu::test]
+    fn test_allowlist_tiers(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(10_000));
+        carol.fund(U256::from(10_000));
+        let (bob_leaf, carol_leaf) = (keccak(bob), keccak(carol));
+        let (a, b) = if bob_leaf <= carol_leaf { (bob_leaf, carol_leaf) } else { (carol_leaf, bob_leaf) };
+        let og_root = keccak([a.as_slice(), b.as_slice()].concat());
+        // A single-leaf tree whose root is the leaf itself.
+        let wl_root = carol_leaf;
+
+        let (og, wl) = (U256::from(1), U256::from(2));
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_tier(og, og_root, U256::from(50), U256::from(2)).unwrap_err());
+        contract.sender(alice).set_tier(og, og_root, U256::from(50), U256::from(2)).unwrap();
+        contract.sender(alice).set_tier(wl, wl_root, U256::from(100), U256::from(1)).unwrap();
+        assert_eq!((og_root, U256::from(50), U256::from(2)), contract.sender(alice).tier(og));
+
+        let first = contract.sender_and_value(bob, U256::from(100)).allowlist_mint_tier(og, vec![carol_leaf], U256::from(2)).unwrap();
+        assert_eq!(U256::from(2), contract.sender(alice).balance_of(bob).unwrap());
+        assert_eq!(bob, contract.sender(alice).owner_of(first + U256::from(1)).unwrap());
+        assert_eq!(U256::from(2), contract.sender(alice).tier_minted(og, bob));
+
+        contract.sender_and_value(carol, U256::from(100)).allowlist_mint_tier(wl, vec![], U256::from(1)).unwrap();
+        let err = contract.sender_and_value(carol, U256::from(50)).allowlist_mint_tier(wl, vec![], U256::from(1)).unwrap_err();
+        assert_eq!(b"Tier cap reached".to_vec(), err);
+        let err = contract.sender_and_value(carol, U256::from(50)).allowlist_mint_tier(wl, vec![bob_leaf], U256::from(1)).unwrap_err();
+        assert_eq!(b"Invalid proof".to_vec(), err);
+        // Bob's proof is only good for the tier it was made for.
+        let err = contract.sender_and_value(bob, U256::from(100)).allowlist_mint_tier(wl, vec![carol_leaf], U256::from(1)).unwrap_err();
+        assert_eq!(b"Invalid proof".to_vec(), err);
+        let err = contract.sender_and_value(bob, U256::from(49)).allowlist_mint_tier(og, vec![carol_leaf], U256::from(1)).unwrap_err();
+        assert_eq!(b"Tier cap reached".to_vec(), err);
+        let err = contract.sender_and_value(bob, U256::from(100)).allowlist_mint_tier(U256::from(3), vec![], U256::from(1)).unwrap_err();
+        assert_eq!(b"Unknown tier".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_allowlist_tier_payments(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(1_000));
+        let (paid, free, huge) = (U256::from(1), U256::from(2), U256::from(3));
+        contract.sender(alice).set_tier(paid, keccak(bob), U256::from(50), U256::ZERO).unwrap();
+        contract.sender(alice).set_tier(free, keccak(bob), U256::ZERO, U256::ZERO).unwrap();
+        contract.sender(alice).set_tier(huge, keccak(bob), U256::MAX, U256::ZERO).unwrap();
+
+        // Each token records its share of the payment, as `public_mint` would.
+        let first = contract.sender_and_value(bob, U256::from(101)).allowlist_mint_tier(paid, vec![], U256::from(2)).unwrap();
+        assert_eq!(U256::from(51), contract.sender(alice).purchases.get(first).paid.get());
+        assert_eq!(U256::from(50), contract.sender(alice).purchases.get(first + U256::from(1)).paid.get());
+        assert_eq!(U256::from(101), contract.sender(alice).spent_by.get(bob));
+
+        let err = contract.sender(bob).allowlist_mint_tier(huge, vec![], U256::from(2)).unwrap_err();
+        assert_eq!(b"Quantity too large".to_vec(), err);
+        contract.sender(alice).set_max_batch_size(U256::ZERO).unwrap();
+        let err = contract.sender(bob).allowlist_mint_tier(free, vec![], U256::from(u64::MAX) + U256::from(1)).unwrap_err();
+        assert_eq!(b"Quantity too large".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_refund(contract: Contract<DEMONFT>, receiver: Contract<EthReceiver>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        let buyer = receiver.address();
+        buyer.fund(U256::from(1000));
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+        let token_id = contract.sender_and_value(buyer, U256::from(100)).public_mint("ipfs://a".into()).unwrap();
+        let second = contract.sender_and_value(buyer, U256::from(100)).public_mint("ipfs://b".into()).unwrap();
+
+        assert_eq!(b"Refunds disabled".to_vec(), contract.sender(buyer).refund(token_id).unwrap_err());
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_refund_window(U256::from(3600)).unwrap_err());
+        contract.sender(alice).set_refund_window(U256::from(3600)).unwrap();
+
+        // Neither someone else nor a later holder can refund.
+        assert_eq!(b"Not minter".to_vec(), contract.sender(bob).refund(token_id).unwrap_err());
+        contract.sender(buyer).transfer_from(buyer, bob, second).unwrap();
+        assert_eq!(b"Not minter".to_vec(), contract.sender(bob).refund(second).unwrap_err());
+
+        contract.sender(alice).pause().unwrap();
+        assert_eq!(b"Paused".to_vec(), contract.sender(buyer).refund(token_id).unwrap_err());
+        contract.sender(alice).unpause().unwrap();
+
+        assert_eq!(U256::from(200), contract.sender(alice).spent_by(buyer));
+        contract.sender(buyer).refund(token_id).unwrap();
+        assert_eq!(U256::from(900), receiver.balance());
+        assert_eq!(U256::from(100), contract.sender(alice).spent_by(buyer));
+        assert!(contract.sender(alice).owner_of(token_id).is_err());
+        assert_eq!(b"Not minter".to_vec(), contract.sender(buyer).refund(token_id).unwrap_err());
+
+        // Pretend the other token was bought more than an hour ago.
+        let third = contract.sender_and_value(buyer, U256::from(100)).public_mint("ipfs://c".into()).unwrap();
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+        contract.sender(alice).purchases.setter(third).minted_at.set(now - U256::from(3601));
+        assert_eq!(b"Refund window closed".to_vec(), contract.sender(buyer).refund(third).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_refund_keeps_fees_and_royalties(
+        contract: Contract<DEMONFT>,
+        platform: Contract<EthReceiver>,
+        treasury: Contract<EthReceiver>,
+        buyer: Contract<EthReceiver>,
+        artist: Contract<EthReceiver>,
+        alice: Address,
+    ) {
+        contract.sender(alice).init("Demo".into(), "DEMO".into(), "ipfs://".into(), U256::ZERO, U256::ZERO, platform.address(), U256::from(1_000));
+        let buyer = buyer.address();
+        buyer.fund(U256::from(1000));
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+        contract.sender(alice).set_refund_window(U256::from(3600)).unwrap();
+        contract.sender(alice).set_royalty(artist.address(), U256::from(500)).unwrap();
+
+        let kept = contract.sender_and_value(buyer, U256::from(100)).public_mint("ipfs://a".into()).unwrap();
+        contract.sender(alice).set_treasury(treasury.address(), true).unwrap();
+        let forwarded = contract.sender_and_value(buyer, U256::from(100)).public_mint("ipfs://b".into()).unwrap();
+        assert_eq!(U256::from(90), treasury.balance());
+        assert_eq!(b"Payment forwarded".to_vec(), contract.sender(buyer).refund(forwarded).unwrap_err());
+
+        // The platform fee is gone, so only what the contract kept comes back.
+        contract.sender_and_value(buyer, U256::from(50)).pay_royalty(kept).unwrap();
+        contract.sender(alice).withdraw_amount(treasury.address(), U256::from(10)).unwrap();
+        assert_eq!(U256::from(130), contract.balance());
+        let err = contract.sender(buyer).refund(kept).unwrap_err();
+        assert_eq!(b"Insufficient balance".to_vec(), err);
+        contract.fund(U256::from(10));
+        contract.sender(buyer).refund(kept).unwrap();
+        assert_eq!(U256::from(50), contract.balance());
+        contract.sender(artist.address()).claim_royalty().unwrap();
+        assert_eq!(U256::from(50), artist.balance());
+    }
+
+    #[motsu::test]
+    fn test_contract_balance(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(1000));
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+        assert_eq!(U256::ZERO, contract.sender(alice).contract_balance());
+
+        contract.sender_and_value(bob, U256::from(100)).public_mint("ipfs://a".into()).unwrap();
+        contract.sender_and_value(bob, U256::from(120)).public_mint("ipfs://b".into()).unwrap();
+        assert_eq!(U256::from(220), contract.sender(alice).contract_balance());
+    }
+
+    #[motsu::test]
+    fn test_mint_cooldown(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_mint_cooldown(U256::from(60)).unwrap_err());
+        contract.sender(alice).set_mint_cooldown(U256::from(60)).unwrap();
+
+        // The owner can airdrop back to back.
+        for _ in 0..3 {
+            contract.sender(alice).mint(bob, String::new()).unwrap();
+        }
+
+        contract.sender(bob).mint(bob, String::new()).unwrap();
+        assert_eq!((false, "Mint cooldown".into()), contract.sender(bob).can_mint(bob, U256::from(1)));
+        // Pretend the last mint was a minute ago.
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+        contract.sender(alice).last_mint_at.setter(bob).set(now - U256::from(60));
+        contract.sender(bob).mint(bob, String::new()).unwrap();
+        assert_eq!(b"Mint cooldown".to_vec(), contract.sender(bob).mint(bob, String::new()).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_can_mint(contract: Contract<DEMONFT>, receiver: Contract<EthReceiver>, alice: Address, bob: Address) {
+        init(&contract, alice, 5);
+        let one = U256::from(1);
+        assert_eq!((true, String::new()), contract.sender(bob).can_mint(bob, one));
+
+        contract.sender(alice).pause().unwrap();
+        assert_eq!((false, "Paused".into()), contract.sender(bob).can_mint(bob, one));
+        contract.sender(alice).unpause().unwrap();
+
+        assert_eq!((false, "Cannot mint to self".into()), contract.sender(bob).can_mint(contract.address(), one));
+        contract.sender(alice).set_allow_contract_recipients(false).unwrap();
+        assert_eq!((false, "Contract recipient".into()), contract.sender(bob).can_mint(receiver.address(), one));
+
+        assert_eq!((false, "Max supply reached".into()), contract.sender(bob).can_mint(bob, U256::from(6)));
+
+        contract.sender(alice).set_max_mints_per_block(U256::from(2)).unwrap();
+        assert_eq!((false, "Block mint cap".into()), contract.sender(bob).can_mint(bob, U256::from(3)));
+
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+        contract.sender(alice).set_max_spend_per_wallet(U256::from(150)).unwrap();
+        assert_eq!((false, "Spend cap reached".into()), contract.sender(bob).can_mint(bob, U256::from(2)));
+        assert_eq!((true, String::new()), contract.sender(bob).can_mint(bob, one));
+
+        // Credits are free through `mint_with_credit`, but `public_mint` still charges.
+        contract.sender(alice).grant_credits(bob, U256::from(2)).unwrap();
+        assert_eq!(U256::ZERO, contract.sender(bob).effective_price_for(bob).unwrap());
+        assert_eq!((false, "Spend cap reached".into()), contract.sender(bob).can_mint(bob, U256::from(2)));
+    }
+
+    #[motsu::test]
+    fn test_remaining_allowance(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address, dave: Address) {
+        init(&contract, alice, 20);
+        bob.fund(U256::from(1_000));
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+        contract.sender(alice).set_max_spend_per_wallet(U256::from(300)).unwrap();
+
+        // Partway to the cap.
+        contract.sender_and_value(bob, U256::from(100)).public_mint(String::new()).unwrap();
+        assert_eq!(U256::from(2), contract.sender(alice).remaining_allowance(bob));
+
+        // At the cap, but with credits.
+        contract.sender(alice).spent_by.setter(carol).set(U256::from(300));
+        contract.sender(alice).grant_credits(carol, U256::from(2)).unwrap();
+        assert_eq!(U256::from(2), contract.sender(alice).remaining_allowance(carol));
+
+        contract.sender(alice).set_free_allocation(dave, U256::from(4)).unwrap();
+        assert_eq!(U256::from(7), contract.sender(alice).remaining_allowance(dave));
+
+        // Never more than the supply left.
+        contract.sender(alice).set_max_spend_per_wallet(U256::ZERO).unwrap();
+        assert_eq!(U256::from(19), contract.sender(alice).remaining_allowance(dave));
+    }
+
+    #[motsu::test]
+    fn test_max_spend_per_wallet(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(1000));
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_max_spend_per_wallet(U256::from(250)).unwrap_err());
+        contract.sender(alice).set_max_spend_per_wallet(U256::from(250)).unwrap();
+
+        contract.sender_and_value(bob, U256::from(100)).public_mint("ipfs://a".into()).unwrap();
+        contract.sender_and_value(bob, U256::from(150)).public_mint("ipfs://b".into()).unwrap();
+        assert_eq!(U256::from(250), contract.sender(alice).spent_by(bob));
+
+        let err = contract.sender_and_value(bob, U256::from(100)).public_mint("ipfs://c".into()).unwrap_err();
+        assert_eq!(b"Spend cap reached".to_vec(), err);
+        assert_eq!(U256::ZERO, contract.sender(alice).spent_by(alice));
+    }
+
+    #[motsu::test]
+    fn test_usd_price(contract: Contract<DEMONFT>, oracle: Contract<MockOracle>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(10).pow(U256::from(18)));
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+        let err = contract.sender(bob).set_usd_price(oracle.address(), U256::from(2500)).unwrap_err();
+        assert_eq!(b"Not owner".to_vec(), err);
+        contract.sender(alice).set_usd_price(oracle.address(), U256::from(2500)).unwrap();
+        assert_eq!(b"Bad oracle".to_vec(), contract.sender(bob).public_mint_price().unwrap_err());
+
+        // $25 at $2000 per ETH is 0.0125 ETH.
+        oracle.sender(alice).set_price(U256::from(200_000));
+        let required = U256::from(12_500_000_000_000_000_u64);
+        assert_eq!(required, contract.sender(bob).public_mint_price().unwrap());
+        let err = contract.sender_and_value(bob, required - U256::from(1)).public_mint("ipfs://a".into()).unwrap_err();
+        assert_eq!(b"Insufficient payment".to_vec(), err);
+        let token_id = contract.sender_and_value(bob, required).public_mint("ipfs://a".into()).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(token_id).unwrap());
+
+        contract.sender(alice).set_usd_price(Address::ZERO, U256::ZERO).unwrap();
+        assert_eq!(U256::from(100), contract.sender(bob).public_mint_price().unwrap());
+    }
+
+    #[motsu::test]
+    fn test_allowlist_usd_price(contract: Contract<DEMONFT>, oracle: Contract<MockOracle>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(10).pow(U256::from(18)));
+        contract.sender(alice).set_allowlisted(bob, true).unwrap();
+        contract.sender(alice).set_allowlist_discount_bps(U256::from(2_000)).unwrap();
+        contract.sender(alice).set_usd_price(oracle.address(), U256::from(2500)).unwrap();
+        oracle.sender(alice).set_price(U256::from(200_000));
+
+        // 20% off the 0.0125 ETH USD price, while `public_price` itself is zero.
+        let required = U256::from(10_000_000_000_000_000_u64);
+        assert_eq!(required, contract.sender(bob).allowlist_price().unwrap());
+        assert_eq!(required, contract.sender(bob).effective_price_for(bob).unwrap());
+        let err = contract.sender(bob).allowlist_mint("ipfs://a".into()).unwrap_err();
+        assert_eq!(b"Insufficient payment".to_vec(), err);
+        contract.sender_and_value(bob, required).allowlist_mint("ipfs://a".into()).unwrap();
+    }
+
+    #[motsu::test]
+    fn test_platform_fee(contract: Contract<DEMONFT>, platform: Contract<EthReceiver>, owner: Contract<EthReceiver>, bob: Address) {
+        contract.sender(owner.address()).init(
+            "Demo".into(),
+            "DEMO".into(),
+            "ipfs://".into(),
+            U256::ZERO,
+            U256::ZERO,
+            platform.address(),
+            U256::from(500),
+        );
+        assert_eq!((platform.address(), U256::from(500)), contract.sender(bob).platform_fee());
+        bob.fund(U256::from(1000));
+        contract.sender(owner.address()).set_public_price(U256::from(200)).unwrap();
+
+        contract.sender_and_value(bob, U256::from(200)).public_mint("ipfs://a".into()).unwrap();
+        assert_eq!(U256::from(10), platform.balance());
+        assert_eq!(U256::from(190), contract.balance());
+
+        contract.sender(owner.address()).withdraw().unwrap();
+        assert_eq!(U256::from(190), owner.balance());
+    }
+
+    #[motsu::test]
+    #[should_panic(expected = "Platform fee too high")]
+    fn test_platform_fee_ceiling(contract: Contract<DEMONFT>, platform: Address, alice: Address) {
+        contract.sender(alice).init("Demo".into(), "DEMO".into(), "ipfs://".into(), U256::ZERO, U256::ZERO, platform, U256::from(1_001));
+    }
+
+    #[motsu::test]
+    #[should_panic(expected = "Empty name/symbol")]
+    fn test_init_empty_name(contract: Contract<DEMONFT>, alice: Address) {
+        contract.sender(alice).init(String::new(), "DEMO".into(), "ipfs://".into(), U256::ZERO, U256::ZERO, Address::ZERO, U256::ZERO);
+    }
+
+    #[motsu::test]
+    #[should_panic(expected = "Empty name/symbol")]
+    fn test_init_empty_symbol(contract: Contract<DEMONFT>, alice: Address) {
+        contract.sender(alice).init("Demo".into(), String::new(), "ipfs://".into(), U256::ZERO, U256::ZERO, Address::ZERO, U256::ZERO);
+    }
+
+    #[motsu::test]
+    fn test_init_validates_strings(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        assert_eq!("Demo", contract.sender(alice).name());
+        assert_eq!("DEMO", contract.sender(alice).symbol());
+
+        contract.sender(alice).set_max_uri_length(U256::from(10)).unwrap();
+        let err = contract.sender(alice).set_base_uri("ipfs://too-long/".into()).unwrap_err();
+        assert_eq!(b"URI too long".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_effective_price_for(contract: Contract<DEMONFT>, alice: Address, free: Address, listed: Address, public: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+        contract.sender(alice).set_allowlist_discount_bps(U256::from(2_500)).unwrap();
+        contract.sender(alice).set_free_allocation(free, U256::from(1)).unwrap();
+        contract.sender(alice).set_allowlisted(free, true).unwrap();
+        contract.sender(alice).set_allowlisted(listed, true).unwrap();
+
+        assert_eq!(U256::ZERO, contract.sender(alice).effective_price_for(free).unwrap());
+        assert_eq!(U256::from(75), contract.sender(alice).effective_price_for(listed).unwrap());
+        assert_eq!(U256::from(100), contract.sender(alice).effective_price_for(public).unwrap());
+
+        contract.sender(alice).grant_credits(public, U256::from(1)).unwrap();
+        assert_eq!(U256::ZERO, contract.sender(alice).effective_price_for(public).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_forward_on_mint(contract: Contract<DEMONFT>, treasury: Contract<EthReceiver>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(1000));
+        contract.sender(alice).set_public_price(U256::from(100)).unwrap();
+
+        // Proceeds stay in the contract by default.
+        contract.sender_and_value(bob, U256::from(100)).public_mint("ipfs://a".into()).unwrap();
+        assert_eq!(U256::from(100), contract.balance());
+
+        assert_eq!(b"Zero address".to_vec(), contract.sender(alice).set_treasury(Address::ZERO, true).unwrap_err());
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_treasury(treasury.address(), true).unwrap_err());
+        contract.sender(alice).set_treasury(treasury.address(), true).unwrap();
+        assert_eq!((treasury.address(), true), contract.sender(alice).treasury());
+
+        contract.sender_and_value(bob, U256::from(120)).public_mint("ipfs://b".into()).unwrap();
+        assert_eq!(U256::from(120), treasury.balance());
+        assert_eq!(U256::from(100), contract.balance());
+    }
+
+    #[motsu::test]
+    fn test_allowlist_discount(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(1000));
+        contract.sender(alice).set_public_price(U256::from(250)).unwrap();
+
+        assert_eq!(b"Not allowlisted".to_vec(), contract.sender(bob).allowlist_mint("ipfs://a".into()).unwrap_err());
+        contract.sender(alice).set_allowlisted(bob, true).unwrap();
+        assert!(contract.sender(alice).is_allowlisted(bob));
+
+        let err = contract.sender(alice).set_allowlist_discount_bps(U256::from(10_001)).unwrap_err();
+        assert_eq!(b"Discount too high".to_vec(), err);
+        contract.sender(alice).set_allowlist_discount_bps(U256::from(2000)).unwrap();
+        assert_eq!(U256::from(200), contract.sender(alice).allowlist_price().unwrap());
+
+        let err = contract.sender_and_value(bob, U256::from(199)).allowlist_mint("ipfs://a".into()).unwrap_err();
+        assert_eq!(b"Insufficient payment".to_vec(), err);
+        let token_id = contract.sender_and_value(bob, U256::from(200)).allowlist_mint("ipfs://a".into()).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(token_id).unwrap());
+
+        // A full discount makes allowlist mints free.
+        contract.sender(alice).set_allowlist_discount_bps(U256::from(10_000)).unwrap();
+        assert_eq!(U256::ZERO, contract.sender(alice).allowlist_price().unwrap());
+        contract.sender(bob).allowlist_mint("ipfs://b".into()).unwrap();
+        assert_eq!(U256::from(2), contract.sender(alice).balance_of(bob).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_auto_approve_operator(contract: Contract<DEMONFT>, alice: Address, bob: Address, staking: Address) {
+        init(&contract, alice, 0);
+        let err = contract.sender(alice).set_auto_approve_operator(Address::ZERO, true).unwrap_err();
+        assert_eq!(b"Zero address".to_vec(), err);
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_auto_approve_operator(staking, true).unwrap_err());
+        contract.sender(alice).set_auto_approve_operator(staking, true).unwrap();
+        assert_eq!((staking, true), contract.sender(alice).auto_approve_operator());
+
+        let token_id = contract.sender(alice).mint(bob, "ipfs://a".into()).unwrap();
+        assert_eq!(staking, contract.sender(alice).get_approved(token_id).unwrap());
+        contract.assert_emitted(&Approval { owner: bob, approved: staking, token_id });
+        let first = contract.sender(alice).batch_mint(bob, uris(2)).unwrap();
+        assert_eq!(staking, contract.sender(alice).get_approved(first + U256::from(1)).unwrap());
+        contract.sender(staking).transfer_from(bob, staking, token_id).unwrap();
+
+        contract.sender(alice).set_auto_approve_operator(staking, false).unwrap();
+        let token_id = contract.sender(alice).mint(bob, "ipfs://b".into()).unwrap();
+        assert_eq!(Address::ZERO, contract.sender(alice).get_approved(token_id).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_editions(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        let edition = U256::from(7);
+        assert_eq!(b"Edition sold out".to_vec(), contract.sender(alice).mint_edition(edition, bob).unwrap_err());
+        assert_eq!(b"Invalid edition".to_vec(), contract.sender(alice).set_edition_cap(U256::ZERO, U256::from(2)).unwrap_err());
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_edition_cap(edition, U256::from(2)).unwrap_err());
+        contract.sender(alice).set_edition_cap(edition, U256::from(2)).unwrap();
+
+        let first = contract.sender(alice).mint_edition(edition, bob).unwrap();
+        let second = contract.sender(alice).mint_edition(edition, alice).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(edition, contract.sender(alice).edition_of(first));
+        assert_eq!(edition, contract.sender(alice).edition_of(second));
+        assert_eq!(bob, contract.sender(alice).owner_of(first).unwrap());
+        assert_eq!((U256::from(2), U256::from(2)), contract.sender(alice).edition_supply(edition));
+
+        assert_eq!(b"Edition sold out".to_vec(), contract.sender(alice).mint_edition(edition, bob).unwrap_err());
+        assert_eq!(b"Cap below minted".to_vec(), contract.sender(alice).set_edition_cap(edition, U256::from(1)).unwrap_err());
+        let plain = contract.sender(alice).mint(bob, "ipfs://plain".into()).unwrap();
+        assert_eq!(U256::ZERO, contract.sender(alice).edition_of(plain));
+    }
+
+    #[motsu::test]
+    fn test_max_uri_length(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(U256::from(DEFAULT_MAX_URI_LENGTH), contract.sender(alice).max_uri_length());
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_max_uri_length(U256::ZERO).unwrap_err());
+        contract.sender(alice).set_max_uri_length(U256::from(10)).unwrap();
+
+        let token_id = contract.sender(alice).mint(alice, "a".repeat(10)).unwrap();
+        assert_eq!(b"URI too long".to_vec(), contract.sender(alice).set_token_uri(token_id, "a".repeat(11)).unwrap_err());
+        assert_eq!(b"URI too long".to_vec(), contract.sender(alice).batch_mint(alice, vec!["a".repeat(11)]).unwrap_err());
+
+        assert_eq!(b"URI too long".to_vec(), contract.sender(alice).mint(alice, "a".repeat(11)).unwrap_err());
+
+        contract.sender(alice).set_max_uri_length(U256::ZERO).unwrap();
+        contract.sender(alice).set_token_uri(token_id, "a".repeat(1_000)).unwrap();
+        assert_eq!("a".repeat(1_000), contract.sender(alice).token_uri(token_id).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_enforce_unique_uri(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).mint(bob, "ipfs://same".into()).unwrap();
+        contract.sender(alice).mint(bob, "ipfs://same".into()).unwrap();
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_enforce_unique_uri(true).unwrap_err());
+        contract.sender(alice).set_enforce_unique_uri(true).unwrap();
+        contract.sender(alice).mint(bob, "ipfs://one".into()).unwrap();
+        contract.sender(alice).mint(bob, "ipfs://two".into()).unwrap();
+        assert_eq!(b"Duplicate URI".to_vec(), contract.sender(alice).mint(bob, "ipfs://one".into()).unwrap_err());
+        let err = contract.sender(alice).batch_mint(bob, vec!["ipfs://three".into(), "ipfs://two".into()]).unwrap_err();
+        assert_eq!(b"Duplicate URI".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_token_global_id(contract: Contract<DEMONFT>, alice: Address) {
+        let mut encoded = [0_u8; 64];
+        encoded[12..32].copy_from_slice(contract.address().as_slice());
+        encoded[32..].copy_from_slice(&U256::from(7).to_be_bytes::<32>());
+        assert_eq!(keccak(encoded), contract.sender(alice).token_global_id(U256::from(7)));
+        assert_ne!(contract.sender(alice).token_global_id(U256::from(7)), contract.sender(alice).token_global_id(U256::from(8)));
+    }
+
+    #[motsu::test]
+    fn test_mint_deterministic(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        let salt = FixedBytes::<32>::repeat_byte(7);
+
+        let token_id = contract.sender(alice).mint_deterministic(bob, salt, "ipfs://det".into()).unwrap();
+        assert_eq!(U256::from_be_bytes(keccak(salt).0), token_id);
+        assert_eq!(bob, contract.sender(alice).owner_of(token_id).unwrap());
+        assert_eq!("ipfs://det", contract.sender(alice).token_uri(token_id).unwrap());
+
+        let err = contract.sender(alice).mint_deterministic(alice, salt, "ipfs://again".into()).unwrap_err();
+        assert_eq!(b"Already minted".to_vec(), err);
+
+        let other = contract.sender(alice).mint_deterministic(alice, FixedBytes::repeat_byte(8), "ipfs://other".into()).unwrap();
+        assert_ne!(token_id, other);
+        assert_eq!(U256::from(2), contract.sender(alice).total_minted());
+        assert_eq!(U256::ZERO, contract.sender(alice).mint(alice, "ipfs://seq".into()).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_balance_snapshots(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).snapshot().unwrap_err());
+
+        let first = contract.sender(alice).snapshot().unwrap();
+        contract.sender(alice).transfer_from(alice, bob, U256::ZERO).unwrap();
+        let second = contract.sender(alice).snapshot().unwrap();
+        let third = contract.sender(alice).snapshot().unwrap();
+        contract.sender(alice).transfer_from(alice, bob, U256::from(1)).unwrap();
+
+        assert_eq!(U256::from(3), contract.sender(bob).balance_of_at(alice, first).unwrap());
+        assert_eq!(U256::ZERO, contract.sender(bob).balance_of_at(bob, first).unwrap());
+        assert_eq!(U256::from(2), contract.sender(bob).balance_of_at(alice, second).unwrap());
+        assert_eq!(U256::from(2), contract.sender(bob).balance_of_at(alice, third).unwrap());
+        assert_eq!(U256::from(1), contract.sender(bob).balance_of_at(bob, third).unwrap());
+        assert_eq!(U256::from(1), contract.sender(bob).balance_of(alice).unwrap());
+        assert_eq!(b"Unknown snapshot".to_vec(), contract.sender(bob).balance_of_at(alice, U256::from(4)).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_reset_token_state(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(bob, uris(1)).unwrap();
+        contract.sender(bob).approve(carol, U256::ZERO).unwrap();
+        contract.sender(bob).lock_for(U256::ZERO, U256::from(60)).unwrap();
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).reset_token_state(U256::ZERO).unwrap_err());
+
+        contract.sender(alice).reset_token_state(U256::ZERO).unwrap();
+        contract.assert_emitted(&TokenStateReset { tokenId: U256::ZERO });
+        assert!(!contract.sender(alice).is_locked(U256::ZERO));
+        assert_eq!(Address::ZERO, contract.sender(alice).get_approved(U256::ZERO).unwrap());
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::ZERO).unwrap());
+        assert!(contract.sender(alice).reset_token_state(U256::from(1)).is_err());
+    }
+
+    #[motsu::test]
+    fn test_supply_milestones(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 8);
+        let milestone = |percent_bps: u64, minted: u64| SupplyMilestone { percentBps: U256::from(percent_bps), minted: U256::from(minted) };
+
+        contract.sender(alice).mint(alice, String::new()).unwrap();
+        assert!(!contract.emitted(&milestone(2_500, 1)));
+        contract.sender(alice).mint(alice, String::new()).unwrap();
+        contract.assert_emitted(&milestone(2_500, 2));
+        // One batch can pass several milestones.
+        contract.sender(alice).batch_mint(alice, vec![String::new(); 4]).unwrap();
+        contract.assert_emitted(&milestone(5_000, 6));
+        contract.assert_emitted(&milestone(7_500, 6));
+        contract.sender(alice).batch_mint(alice, vec![String::new(); 2]).unwrap();
+        contract.assert_emitted(&milestone(10_000, 8));
+        assert_eq!(0b1111, contract.sender(alice).supply_milestones.get().to::<u8>());
+    }
+
+    #[motsu::test]
+    fn test_tokens_of_owner(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert!(contract.sender(alice).tokens_of_owner(alice).is_empty());
+
+        // Move out the first, middle and last of a 3-token list, one fresh list each.
+        for position in 0..3 {
+            let first = contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+            let ids: Vec<U256> = (0..3).map(|i| first + U256::from(i)).collect();
+            assert_eq!(ids, contract.sender(alice).tokens_of_owner(alice));
+
+            contract.sender(alice).transfer_from(alice, bob, ids[position]).unwrap();
+            let mut remaining = contract.sender(alice).tokens_of_owner(alice);
+            remaining.sort();
+            let expected: Vec<U256> = ids.iter().copied().filter(|&id| id != ids[position]).collect();
+            assert_eq!(expected, remaining);
+            for (index, id) in contract.sender(alice).tokens_of_owner(alice).into_iter().enumerate() {
+                assert_eq!(id, contract.sender(alice).token_of_owner_by_index(alice, U256::from(index)).unwrap());
+                assert_eq!(U256::from(index), contract.sender(alice).owned_index.get(id));
+            }
+            assert!(contract.sender(alice).token_of_owner_by_index(alice, U256::from(2)).is_err());
+
+            for id in contract.sender(alice).tokens_of_owner(alice) {
+                contract.sender(alice).burn(id).unwrap();
+            }
+            assert!(contract.sender(alice).tokens_of_owner(alice).is_empty());
+        }
+
+        let mut received = contract.sender(bob).tokens_of_owner(bob);
+        received.sort();
+        assert_eq!(vec![U256::ZERO, U256::from(4), U256::from(8)], received);
+        assert_eq!(U256::from(3), contract.sender(bob).balance_of(bob).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_staked_tokens_of(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        assert!(contract.sender(alice).staked_tokens_of(alice).is_empty());
+
+        contract.sender(alice).stake(U256::ZERO).unwrap();
+        contract.sender(alice).stake(U256::from(2)).unwrap();
+        assert_eq!(vec![U256::ZERO, U256::from(2)], contract.sender(alice).staked_tokens_of(alice));
+
+        contract.sender(alice).unstake(U256::ZERO).unwrap();
+        assert_eq!(vec![U256::from(2)], contract.sender(alice).staked_tokens_of(alice));
+        assert!(contract.sender(alice).staked_tokens_of(bob).is_empty());
+
+        // The moved token can still be unstaked from its new slot.
+        contract.sender(alice).unstake(U256::from(2)).unwrap();
+        assert!(contract.sender(alice).staked_tokens_of(alice).is_empty());
+    }
+
+    #[motsu::test]
+    fn test_stake_runs_transfer_checks(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+        contract.sender(alice).lock_for(U256::ZERO, U256::from(60)).unwrap();
+        assert_eq!(b"Token locked".to_vec(), contract.sender(alice).stake(U256::ZERO).unwrap_err());
+
+        contract.sender(alice).pause().unwrap();
+        assert_eq!(b"Paused".to_vec(), contract.sender(alice).stake(U256::from(1)).unwrap_err());
+        contract.sender(alice).unpause().unwrap();
+
+        contract.sender(alice).set_min_hold_seconds(U256::from(60)).unwrap();
+        assert_eq!(b"Hold period active".to_vec(), contract.sender(alice).stake(U256::from(1)).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_stake_skips_receiver_and_fee_policies(
+        contract: Contract<DEMONFT>,
+        treasury: Contract<EthReceiver>,
+        alice: Address,
+        bob: Address,
+        carol: Address,
+    ) {
+        init(&contract, alice, 0);
+        contract.sender(alice).mint(bob, String::new()).unwrap();
+        contract.sender(alice).set_treasury(treasury.address(), false).unwrap();
+        contract.sender(alice).set_transfer_fee(U256::from(10)).unwrap();
+        contract.sender(alice).set_receiver_allowlist_enabled(true).unwrap();
+
+        contract.sender(bob).stake(U256::ZERO).unwrap();
+        assert_eq!(bob, contract.sender(bob).staker_of(U256::ZERO));
+        contract.sender(bob).unstake(U256::ZERO).unwrap();
+        assert_eq!(bob, contract.sender(bob).owner_of(U256::ZERO).unwrap());
+        assert_eq!(U256::ZERO, treasury.balance());
+
+        // A real transfer still has to pass both.
+        let err = contract.sender(bob).transfer_from(bob, carol, U256::ZERO).unwrap_err();
+        assert_eq!(b"Receiver not allowed".to_vec(), err);
+        contract.sender(alice).set_allowed_receiver(carol, true).unwrap();
+        let err = contract.sender(bob).transfer_from(bob, carol, U256::ZERO).unwrap_err();
+        assert_eq!(b"Insufficient transfer fee".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_max_mints_per_block(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_max_mints_per_block(U256::from(3)).unwrap_err());
+        contract.sender(alice).set_max_mints_per_block(U256::from(3)).unwrap();
+
+        contract.sender(bob).mint(bob, "ipfs://a".into()).unwrap();
+        contract.sender(bob).batch_mint(bob, uris(2)).unwrap();
+        assert_eq!(U256::from(3), contract.sender(bob).mints_this_block());
+        assert_eq!(b"Block mint cap".to_vec(), contract.sender(bob).mint(bob, "ipfs://b".into()).unwrap_err());
+        assert_eq!(b"Block mint cap".to_vec(), contract.sender(bob).batch_mint(bob, uris(1)).unwrap_err());
+
+        // The test VM stays on one block, so clear its counter to stand in for the
+        // empty counter of the next block.
+        let block = U256::from(contract.sender(bob).vm().block_number());
+        contract.sender(bob).block_mints.setter(block).set(U256::ZERO);
+        assert_eq!(U256::ZERO, contract.sender(bob).mints_this_block());
+        contract.sender(bob).mint(bob, "ipfs://b".into()).unwrap();
+    }
+
+    #[motsu::test]
+    fn test_is_sold_out(contract: Contract<DEMONFT>, uncapped: Contract<DEMONFT>, alice: Address) {
+        contract.sender(alice).init("Demo".into(), "DEMO".into(), "ipfs://".into(), U256::from(3), U256::from(1), Address::ZERO, U256::ZERO);
+        assert!(!contract.sender(alice).is_sold_out());
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+        assert!(!contract.sender(alice).is_sold_out());
+        contract.sender(alice).mint(alice, String::new()).unwrap();
+        assert!(contract.sender(alice).is_sold_out());
+
+        init(&uncapped, alice, 0);
+        uncapped.sender(alice).batch_mint(alice, uris(5)).unwrap();
+        assert!(!uncapped.sender(alice).is_sold_out());
+    }
+
+    #[motsu::test]
+    fn test_start_id(contract: Contract<DEMONFT>, alice: Address) {
+        contract.sender(alice).init("Demo".into(), "DEMO".into(), "ipfs://".into(), U256::from(3), U256::from(1), Address::ZERO, U256::ZERO);
+        assert_eq!(U256::from(1), contract.sender(alice).start_id());
+
+        assert_eq!(U256::from(1), contract.sender(alice).mint(alice, "ipfs://first".into()).unwrap());
+        assert_eq!(U256::from(2), contract.sender(alice).batch_mint(alice, uris(2)).unwrap());
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(3)).unwrap());
+        assert!(contract.sender(alice).owner_of(U256::ZERO).is_err());
+        assert_eq!("ipfs://first", contract.sender(alice).token_uri(U256::from(1)).unwrap());
+
+        // max_supply counts tokens, so the last id is start_id + max_supply - 1.
+        let err = contract.sender(alice).mint(alice, "ipfs://over".into()).unwrap_err();
+        let decoded = MaxSupplyReached::abi_decode(&err, true).unwrap();
+        assert_eq!(U256::from(3), decoded.minted);
+        assert_eq!(U256::from(3), contract.sender(alice).total_minted());
+
+        contract.sender(alice).reveal().unwrap();
+        contract.assert_emitted(&BatchMetadataUpdate { _fromTokenId: U256::from(1), _toTokenId: U256::from(3) });
+    }
+
+    #[motsu::test]
+    fn test_burn(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+
+        assert!(contract.sender(bob).burn(U256::from(1)).is_err());
+        contract.sender(alice).burn(U256::from(1)).unwrap();
+        contract.assert_emitted(&Transfer { from: alice, to: Address::ZERO, token_id: U256::from(1) });
+
+        assert!(contract.sender(alice).owner_of(U256::from(1)).is_err());
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(2)).unwrap());
+        assert_eq!(U256::from(2), contract.sender(alice).balance_of(alice).unwrap());
+        assert!(contract.sender(alice).burn(U256::from(1)).is_err());
+    }
+
+    #[motsu::test]
+    fn test_burn_for_refund(contract: Contract<DEMONFT>, holder: Contract<EthReceiver>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        let token_id = contract.sender(alice).mint(holder.address(), "ipfs://a".into()).unwrap();
+        assert_eq!(b"No buyback".to_vec(), contract.sender(holder.address()).burn_for_refund(token_id).unwrap_err());
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_buyback_amount(U256::from(40)).unwrap_err());
+        contract.sender(alice).set_buyback_amount(U256::from(40)).unwrap();
+
+        let err = contract.sender(holder.address()).burn_for_refund(token_id).unwrap_err();
+        assert_eq!(b"Insufficient balance".to_vec(), err);
+
+        contract.fund(U256::from(100));
+        assert!(contract.sender(bob).burn_for_refund(token_id).is_err());
+        contract.sender(holder.address()).burn_for_refund(token_id).unwrap();
+        assert_eq!(U256::from(40), holder.balance());
+        assert_eq!(U256::from(60), contract.balance());
+        assert!(contract.sender(alice).owner_of(token_id).is_err());
+    }
+
+    #[motsu::test]
+    fn test_nonexistent_token_error(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        let token_id = contract.sender(alice).mint(alice, "ipfs://a".into()).unwrap();
+        contract.sender(alice).burn(token_id).unwrap();
+
+        for id in [token_id, U256::from(99)] {
+            let expected: Vec<u8> = DemoNftError::NonexistentToken(NonexistentToken { id }).into();
+            assert_eq!(expected, contract.sender(alice).token_uri(id).unwrap_err());
+            assert_eq!(expected, contract.sender(alice).token_http_uri(id).unwrap_err());
+            assert_eq!(expected, contract.sender(alice).owner_of(id).unwrap_err());
+            assert_eq!(expected, contract.sender(alice).get_approved(id).unwrap_err());
+        }
+    }
+
+    #[motsu::test]
+    fn test_batch_burn(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(4)).unwrap();
+        let bobs = contract.sender(alice).mint(bob, "ipfs://bob".into()).unwrap();
+
+        contract.sender(alice).batch_burn(vec![U256::ZERO, U256::from(1), U256::from(3)]).unwrap();
+        for id in [0, 1, 3] {
+            assert!(contract.sender(alice).owner_of(U256::from(id)).is_err());
+            assert!(contract.sender(alice).token_uris.get(U256::from(id)).is_empty());
+        }
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(2)).unwrap());
+        assert_eq!(U256::from(1), contract.sender(alice).balance_of(alice).unwrap());
+
+        assert!(contract.sender(alice).batch_burn(vec![U256::from(2), bobs]).is_err());
+        assert_eq!(bob, contract.sender(alice).owner_of(bobs).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_total_burned(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(4)).unwrap();
+        assert_eq!(U256::ZERO, contract.sender(alice).total_burned());
+
+        contract.sender(alice).burn(U256::ZERO).unwrap();
+        contract.sender(alice).batch_burn(vec![U256::from(1), U256::from(3)]).unwrap();
+        assert_eq!(U256::from(3), contract.sender(alice).total_burned());
+        assert_eq!(U256::from(4), contract.sender(alice).total_minted());
+    }
+
+    #[motsu::test]
+    fn test_has_ever_minted(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert!(!contract.sender(alice).has_ever_minted(bob));
+
+        let token_id = contract.sender(bob).mint(bob, "ipfs://a".into()).unwrap();
+        assert!(contract.sender(alice).has_ever_minted(bob));
+
+        contract.sender(bob).burn(token_id).unwrap();
+        assert!(contract.sender(alice).has_ever_minted(bob));
+        assert!(!contract.sender(alice).has_ever_minted(alice));
+    }
+
+    #[motsu::test]
+    fn test_owners_of(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        contract.sender(alice).transfer_from(alice, bob, U256::from(1)).unwrap();
+
+        let ids = vec![U256::ZERO, U256::from(1), U256::from(2), U256::from(3), U256::from(100)];
+        let owners = contract.sender(alice).owners_of(ids).unwrap();
+        assert_eq!(vec![alice, bob, alice, Address::ZERO, Address::ZERO], owners);
+
+        contract.sender(alice).set_max_batch_size(U256::from(2)).unwrap();
+        let err = contract.sender(alice).owners_of(vec![U256::ZERO; 3]).unwrap_err();
+        assert_eq!(b"Batch too large".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_existence_and_owners(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        contract.sender(alice).transfer_from(alice, bob, U256::from(2)).unwrap();
+        contract.sender(alice).burn(U256::from(1)).unwrap();
+
+        let ids = vec![U256::ZERO, U256::from(1), U256::from(2), U256::from(3)];
+        let result = contract.sender(alice).existence_and_owners(ids).unwrap();
+        assert_eq!(vec![(true, alice), (true, Address::ZERO), (true, bob), (false, Address::ZERO)], result);
+    }
+
+    #[motsu::test]
+    fn test_uris_of(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+        let ids = vec![U256::ZERO, U256::from(1), U256::from(2)];
+        let expected = vec![(true, "ipfs://token-0".into()), (true, "ipfs://token-1".into()), (false, String::new())];
+        assert_eq!(expected, contract.sender(alice).uris_of(ids.clone()).unwrap());
+
+        contract.sender(alice).set_unrevealed_uri("ipfs://hidden".into()).unwrap();
+        let expected = vec![(true, "ipfs://hidden".into()), (true, "ipfs://hidden".into()), (false, String::new())];
+        assert_eq!(expected, contract.sender(alice).uris_of(ids).unwrap());
+
+        let err = contract.sender(alice).uris_of(vec![U256::ZERO; DEFAULT_MAX_BATCH_SIZE as usize + 1]).unwrap_err();
+        assert_eq!(b"Batch too large".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_approvals_of(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        contract.sender(alice).approve(bob, U256::ZERO).unwrap();
+        contract.sender(alice).approve(carol, U256::from(2)).unwrap();
+        contract.sender(alice).set_approval_for_all(carol, true).unwrap();
+
+        let ids = vec![U256::ZERO, U256::from(1), U256::from(2), U256::from(3)];
+        let (for_all, approved) = contract.sender(bob).approvals_of(alice, carol, ids.clone()).unwrap();
+        assert!(for_all);
+        assert_eq!(vec![bob, Address::ZERO, carol, Address::ZERO], approved);
+        assert!(!contract.sender(bob).approvals_of(alice, bob, ids).unwrap().0);
+    }
+
+    fn sign_permit_for_all(contract: &Contract<DEMONFT>, owner: &Account, operator: Address, approved: bool, deadline: U256) -> Bytes {
+        let nonce = contract.sender(owner.address()).nonces(owner.address());
+        let struct_hash = keccak(
+            (FixedBytes::from(PERMIT_FOR_ALL_TYPEHASH), owner.address(), operator, approved, nonce, deadline).abi_encode(),
+        );
+        let hash = contract.sender(owner.address()).hash_typed_data_v4(struct_hash);
+        owner.signer().sign_hash_sync(&hash).unwrap().as_bytes().to_vec().into()
+    }
+
+    fn sign_permit_batch(contract: &Contract<DEMONFT>, owner: &Account, operator: Address, token_ids: &[U256], deadline: U256) -> Bytes {
+        let nonce = contract.sender(owner.address()).nonces(owner.address());
+        let struct_hash = keccak(
+            (FixedBytes::from(PERMIT_BATCH_TYPEHASH), owner.address(), operator, hash_token_ids(token_ids), nonce, deadline)
+                .abi_encode(),
+        );
+        let hash = contract.sender(owner.address()).hash_typed_data_v4(struct_hash);
+        owner.signer().sign_hash_sync(&hash).unwrap().as_bytes().to_vec().into()
+    }
+
+    #[motsu::test]
+    fn test_permit_batch(contract: Contract<DEMONFT>, alice: Account, bob: Address, relayer: Address) {
+        init(&contract, alice.address(), 0);
+        contract.sender(alice.address()).batch_mint(alice.address(), uris(3)).unwrap();
+        let deadline = U256::from(contract.sender(relayer).vm().block_timestamp() + 3_600);
+        let ids = vec![U256::ZERO, U256::from(2)];
+
+        // A signature over one id list doesn't verify for another.
+        let signature = sign_permit_batch(&contract, &alice, bob, &ids, deadline);
+        let tampered = vec![U256::ZERO, U256::from(1)];
+        let err = contract.sender(relayer).permit_batch(alice.address(), bob, tampered, deadline, signature.clone()).unwrap_err();
+        assert_eq!(b"Invalid signature".to_vec(), err);
+
+        contract.sender(relayer).permit_batch(alice.address(), bob, ids.clone(), deadline, signature.clone()).unwrap();
+        contract.assert_emitted(&Approval { owner: alice.address(), approved: bob, token_id: U256::from(2) });
+        assert_eq!(bob, contract.sender(relayer).get_approved(U256::ZERO).unwrap());
+        assert_eq!(Address::ZERO, contract.sender(relayer).get_approved(U256::from(1)).unwrap());
+        assert_eq!(bob, contract.sender(relayer).get_approved(U256::from(2)).unwrap());
+        assert_eq!(U256::from(1), contract.sender(relayer).nonces(alice.address()));
+
+        let err = contract.sender(relayer).permit_batch(alice.address(), bob, ids, deadline, signature).unwrap_err();
+        assert_eq!(b"Invalid signature".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_permit_batch_expired(contract: Contract<DEMONFT>, alice: Account, bob: Address) {
+        init(&contract, alice.address(), 0);
+        contract.sender(alice.address()).mint(alice.address(), String::new()).unwrap();
+        let deadline = U256::from(contract.sender(bob).vm().block_timestamp() - 1);
+
+        let signature = sign_permit_batch(&contract, &alice, bob, &[U256::ZERO], deadline);
+        let err = contract.sender(bob).permit_batch(alice.address(), bob, vec![U256::ZERO], deadline, signature).unwrap_err();
+        assert_eq!(b"Permit expired".to_vec(), err);
+        assert_eq!(Address::ZERO, contract.sender(bob).get_approved(U256::ZERO).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_revoke_all_approvals(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address, dave: Address) {
+        init(&contract, alice, 0);
+        for operator in [bob, carol, dave] {
+            contract.sender(alice).set_approval_for_all(operator, true).unwrap();
+        }
+        contract.sender(alice).set_approval_for_all(bob, true).unwrap();
+        contract.sender(bob).set_approval_for_all(carol, true).unwrap();
+
+        contract.sender(alice).revoke_all_approvals().unwrap();
+        for operator in [bob, carol, dave] {
+            assert!(!contract.sender(alice).is_approved_for_all(alice, operator));
+        }
+        contract.assert_emitted(&ApprovalForAll { owner: alice, operator: dave, approved: false });
+        // Other owners' approvals are untouched.
+        assert!(contract.sender(alice).is_approved_for_all(bob, carol));
+
+        contract.sender(alice).set_approval_for_all(carol, true).unwrap();
+        contract.sender(alice).revoke_all_approvals().unwrap();
+        assert!(!contract.sender(alice).is_approved_for_all(alice, carol));
+    }
+
+    #[motsu::test]
+    fn test_permit_for_all(contract: Contract<DEMONFT>, alice: Account, bob: Address, relayer: Address) {
+        init(&contract, alice.address(), 0);
+        let deadline = U256::from(contract.sender(relayer).vm().block_timestamp() + 3_600);
+
+        let signature = sign_permit_for_all(&contract, &alice, bob, true, deadline);
+        contract.sender(relayer).permit_for_all(alice.address(), bob, true, deadline, signature.clone()).unwrap();
+        assert!(contract.sender(relayer).is_approved_for_all(alice.address(), bob));
+        assert_eq!(U256::from(1), contract.sender(relayer).nonces(alice.address()));
+
+        // The nonce moved on, so the same signature can't be replayed.
+        let err = contract.sender(relayer).permit_for_all(alice.address(), bob, true, deadline, signature).unwrap_err();
+        assert_eq!(b"Invalid signature".to_vec(), err);
+
+        // A signature for different arguments doesn't verify.
+        let signature = sign_permit_for_all(&contract, &alice, bob, false, deadline);
+        let err = contract.sender(relayer).permit_for_all(alice.address(), relayer, false, deadline, signature).unwrap_err();
+        assert_eq!(b"Invalid signature".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_permit_for_all_expired(contract: Contract<DEMONFT>, alice: Account, bob: Address) {
+        init(&contract, alice.address(), 0);
+        let deadline = U256::from(contract.sender(bob).vm().block_timestamp() - 1);
+
+        let signature = sign_permit_for_all(&contract, &alice, bob, true, deadline);
+        let err = contract.sender(bob).permit_for_all(alice.address(), bob, true, deadline, signature).unwrap_err();
+        assert_eq!(b"Permit expired".to_vec(), err);
+        assert!(!contract.sender(bob).is_approved_for_all(alice.address(), bob));
+    }
+
+    #[motsu::test]
+    fn test_tip(contract: Contract<DEMONFT>, holder: Contract<EthReceiver>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(100));
+        let token_id = contract.sender(alice).mint(holder.address(), String::new()).unwrap();
+
+        contract.sender_and_value(bob, U256::from(30)).tip(token_id).unwrap();
+        contract.sender_and_value(bob, U256::from(20)).tip(token_id).unwrap();
+        assert_eq!(U256::from(50), holder.balance());
+        assert_eq!(U256::from(50), contract.sender(bob).tips_received(token_id));
+        assert_eq!(U256::ZERO, contract.balance());
+        assert!(contract.sender_and_value(bob, U256::from(10)).tip(U256::from(1)).is_err());
+    }
+
+    #[motsu::test]
+    fn test_tip_staked(contract: Contract<DEMONFT>, holder: Contract<EthReceiver>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        bob.fund(U256::from(100));
+        let token_id = contract.sender(alice).mint(holder.address(), String::new()).unwrap();
+        contract.sender(holder.address()).stake(token_id).unwrap();
+
+        contract.sender_and_value(bob, U256::from(30)).tip(token_id).unwrap();
+        assert_eq!(U256::from(30), holder.balance());
+        assert_eq!(U256::ZERO, contract.balance());
+        assert_eq!(U256::from(30), contract.sender(bob).tips_received(token_id));
+    }
+
+    #[motsu::test]
+    fn test_free_balance_shortfall(contract: Contract<DEMONFT>, treasury: Contract<EthReceiver>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.fund(U256::from(40));
+        // Pretend more royalties are owed than the contract holds.
+        contract.sender(alice).total_pending_royalties.set(U256::from(100));
+
+        contract.sender(alice).withdraw_to(treasury.address()).unwrap();
+        contract.assert_emitted(&EthWithdrawn { to: treasury.address(), amount: U256::ZERO });
+        assert_eq!(U256::from(40), contract.balance());
+        let err = contract.sender(alice).withdraw_amount(treasury.address(), U256::from(1)).unwrap_err();
+        assert_eq!(b"Insufficient balance".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_claim_royalty(
+        contract: Contract<DEMONFT>,
+        owner: Contract<EthReceiver>,
+        artist: Contract<EthReceiver>,
+        holder: Contract<EthReceiver>,
+        marketplace: Address,
+    ) {
+        init(&contract, owner.address(), 0);
+        marketplace.fund(U256::from(1000));
+        let first = contract.sender(owner.address()).mint(holder.address(), "ipfs://a".into()).unwrap();
+        let second = contract.sender(owner.address()).mint(holder.address(), "ipfs://b".into()).unwrap();
+        contract.sender(owner.address()).set_token_royalty(first, artist.address(), U256::from(500)).unwrap();
+        // The contract escrows the default royalty for the holders.
+        contract.sender(owner.address()).set_royalty(contract.address(), U256::from(500)).unwrap();
+
+        contract.sender_and_value(marketplace, U256::from(30)).pay_royalty(first).unwrap();
+        contract.sender_and_value(marketplace, U256::from(20)).pay_royalty(second).unwrap();
+        contract.sender_and_value(marketplace, U256::from(45)).pay_royalty(first).unwrap();
+        assert_eq!(U256::from(75), contract.sender(owner.address()).pending_royalty(artist.address()));
+        assert_eq!(U256::from(20), contract.sender(owner.address()).pending_royalty(holder.address()));
+
+        // Mint proceeds can be withdrawn without touching the royalties.
+        contract.fund(U256::from(100));
+        contract.sender(owner.address()).withdraw().unwrap();
+        assert_eq!(U256::from(100), owner.balance());
+
+        assert_eq!(U256::from(75), contract.sender(artist.address()).claim_royalty().unwrap());
+        assert_eq!(U256::from(20), contract.sender(holder.address()).claim_royalty().unwrap());
+        assert_eq!(U256::from(75), artist.balance());
+        assert_eq!(U256::from(20), holder.balance());
+        assert_eq!(U256::ZERO, contract.balance());
+        assert_eq!(b"Nothing to claim".to_vec(), contract.sender(artist.address()).claim_royalty().unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_flat_royalty(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(b"Royalty too high".to_vec(), contract.sender(alice).set_royalty(bob, U256::from(10_001)).unwrap_err());
+        contract.sender(alice).set_royalty(bob, U256::from(500)).unwrap();
+        assert_eq!((bob, U256::from(50)), contract.sender(alice).royalty_info(U256::ZERO, U256::from(1_000)));
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_flat_royalty(true, U256::from(7)).unwrap_err());
+        contract.sender(alice).set_flat_royalty(true, U256::from(7)).unwrap();
+        assert_eq!((true, U256::from(7)), contract.sender(alice).flat_royalty());
+        for price in [0_u64, 1_000, 1_000_000] {
+            assert_eq!((bob, U256::from(7)), contract.sender(alice).royalty_info(U256::ZERO, U256::from(price)));
+        }
+
+        contract.sender(alice).set_flat_royalty(false, U256::ZERO).unwrap();
+        assert_eq!((bob, U256::from(50)), contract.sender(alice).royalty_info(U256::ZERO, U256::from(1_000)));
+    }
+
+    #[motsu::test]
+    fn test_royalty_falls_back_to_owner(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        let price = U256::from(1_000);
+        assert_eq!((Address::ZERO, U256::ZERO), contract.sender(bob).royalty_info(U256::ZERO, price));
+
+        contract.sender(alice).set_royalty(Address::ZERO, U256::from(500)).unwrap();
+        assert_eq!((alice, U256::from(50)), contract.sender(bob).royalty_info(U256::ZERO, price));
+
+        contract.sender(alice).set_royalty(bob, U256::from(500)).unwrap();
+        assert_eq!((bob, U256::from(50)), contract.sender(bob).royalty_info(U256::ZERO, price));
+    }
+
+    #[motsu::test]
+    fn test_royalty_follows_owner(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+        contract.sender(alice).set_royalty(carol, U256::from(500)).unwrap();
+        let price = U256::from(1_000);
+
+        contract.sender(alice).transfer_from(alice, bob, U256::ZERO).unwrap();
+        assert_eq!((carol, U256::from(50)), contract.sender(alice).royalty_info(U256::ZERO, price));
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_royalty_follows_owner(true).unwrap_err());
+        contract.sender(alice).set_royalty_follows_owner(true).unwrap();
+        assert_eq!((bob, U256::from(50)), contract.sender(alice).royalty_info(U256::ZERO, price));
+        assert_eq!((alice, U256::from(50)), contract.sender(alice).royalty_info(U256::from(1), price));
+
+        contract.sender(bob).transfer_from(bob, carol, U256::ZERO).unwrap();
+        assert_eq!((carol, U256::from(50)), contract.sender(alice).royalty_info(U256::ZERO, price));
+        contract.sender(alice).burn(U256::from(1)).unwrap();
+        assert_eq!((Address::ZERO, U256::ZERO), contract.sender(alice).royalty_info(U256::from(1), price));
+    }
+
+    #[motsu::test]
+    fn test_cannot_mint_to_self(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        let err = contract.sender(alice).mint(contract.address(), "ipfs://a".into()).unwrap_err();
+        assert_eq!(b"Cannot mint to self".to_vec(), err);
+        let err = contract.sender(alice).batch_mint(contract.address(), uris(2)).unwrap_err();
+        assert_eq!(b"Cannot mint to self".to_vec(), err);
+
+        let token_id = contract.sender(alice).mint(alice, "ipfs://a".into()).unwrap();
+        contract.sender(alice).stake(token_id).unwrap();
+        assert_eq!(contract.address(), contract.sender(alice).owner_of(token_id).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_receiver_allowlist(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(bob, uris(3)).unwrap();
+        // Off by default, so anyone can receive.
+        contract.sender(bob).transfer_from(bob, carol, U256::ZERO).unwrap();
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_receiver_allowlist_enabled(true).unwrap_err());
+        contract.sender(alice).set_receiver_allowlist_enabled(true).unwrap();
+        contract.sender(alice).set_allowed_receiver(carol, true).unwrap();
+        assert!(contract.sender(alice).is_allowed_receiver(alice));
+        assert!(!contract.sender(alice).is_allowed_receiver(bob));
+
+        contract.sender(bob).transfer_from(bob, carol, U256::from(1)).unwrap();
+        contract.sender(bob).transfer_from(bob, alice, U256::from(2)).unwrap();
+        contract.sender(alice).mint(carol, String::new()).unwrap();
+        assert_eq!((false, "Receiver not allowed".into()), contract.sender(alice).can_mint(bob, U256::from(1)));
+
+        contract.sender(alice).set_allowed_receiver(carol, false).unwrap();
+        let err = contract.sender(alice).transfer_from(alice, carol, U256::from(2)).unwrap_err();
+        assert_eq!(b"Receiver not allowed".to_vec(), err);
+        assert_eq!(b"Receiver not allowed".to_vec(), contract.sender(alice).mint(bob, String::new()).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_allow_contract_recipients(contract: Contract<DEMONFT>, receiver: Contract<EthReceiver>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert!(contract.sender(alice).allow_contract_recipients());
+        contract.sender(alice).mint(receiver.address(), "ipfs://a".into()).unwrap();
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_allow_contract_recipients(false).unwrap_err());
+        contract.sender(alice).set_allow_contract_recipients(false).unwrap();
+        assert!(!contract.sender(alice).allow_contract_recipients());
+
+        contract.sender(alice).mint(bob, "ipfs://b".into()).unwrap();
+        let err = contract.sender(alice).mint(receiver.address(), "ipfs://c".into()).unwrap_err();
+        assert_eq!(b"Contract recipient".to_vec(), err);
+        let err = contract.sender(alice).batch_mint(receiver.address(), uris(2)).unwrap_err();
+        assert_eq!(b"Contract recipient".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_set_next_id(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+        for id in [50, 10, 100] {
+            contract.sender(alice).set_token_price(U256::from(id), U256::from(1)).unwrap();
+        }
+        bob.fund(U256::from(3));
+        contract.sender_and_value(bob, U256::from(1)).buy(U256::from(50), String::new()).unwrap();
+
+        contract.sender(alice).set_next_id(U256::from(51)).unwrap();
+        assert_eq!(U256::from(51), contract.sender(alice).next_token_id());
+        assert_eq!(U256::from(51), contract.sender(alice).mint(alice, String::new()).unwrap());
+        assert_eq!(U256::from(4), contract.sender(alice).total_minted());
+
+        // The skipped ids belong to nobody, even once their neighbours move.
+        contract.sender(alice).transfer_from(alice, bob, U256::from(1)).unwrap();
+        contract.sender(bob).transfer_from(bob, alice, U256::from(50)).unwrap();
+        for id in [2, 10, 49] {
+            assert!(contract.sender(alice).owner_of(U256::from(id)).is_err(), "{id}");
+        }
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(50)).unwrap());
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(51)).unwrap());
+
+        // They can still be minted explicitly.
+        contract.sender_and_value(bob, U256::from(1)).buy(U256::from(10), String::new()).unwrap();
+        contract.sender(bob).transfer_from(bob, alice, U256::from(10)).unwrap();
+        assert_eq!(alice, contract.sender(alice).owner_of(U256::from(10)).unwrap());
+        assert!(contract.sender(alice).owner_of(U256::from(11)).is_err());
+
+        contract.sender_and_value(bob, U256::from(1)).buy(U256::from(100), String::new()).unwrap();
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_next_id(U256::from(101)).unwrap_err());
+        assert_eq!(b"Counter moves forward only".to_vec(), contract.sender(alice).set_next_id(U256::from(51)).unwrap_err());
+        assert_eq!(b"Below highest id".to_vec(), contract.sender(alice).set_next_id(U256::from(100)).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_highest_minted_id(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(U256::ZERO, contract.sender(alice).highest_minted_id());
+
+        for id in [100, 5] {
+            contract.sender(alice).set_token_price(U256::from(id), U256::from(1)).unwrap();
+        }
+        alice.fund(U256::from(2));
+        contract.sender_and_value(alice, U256::from(1)).buy(U256::from(100), "ipfs://100".into()).unwrap();
+        contract.sender_and_value(alice, U256::from(1)).buy(U256::from(5), "ipfs://5".into()).unwrap();
+        assert_eq!(U256::from(100), contract.sender(alice).highest_minted_id());
+
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        assert_eq!(U256::from(100), contract.sender(alice).highest_minted_id());
+
+        contract.sender(alice).set_append_version_query(true).unwrap();
+        contract.assert_emitted(&BatchMetadataUpdate { _fromTokenId: U256::ZERO, _toTokenId: U256::from(100) });
+    }
+
+    #[motsu::test]
+    fn test_next_token_id(contract: Contract<DEMONFT>, alice: Address) {
+        contract.sender(alice).init("Demo".into(), "DEMO".into(), "ipfs://".into(), U256::ZERO, U256::from(1), Address::ZERO, U256::ZERO);
+        assert_eq!(U256::from(1), contract.sender(alice).next_token_id());
+
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+        assert_eq!(U256::from(3), contract.sender(alice).next_token_id());
+
+        // Ids sold individually are skipped.
+        alice.fund(U256::from(1));
+        contract.sender(alice).set_token_price(U256::from(3), U256::from(1)).unwrap();
+        contract.sender_and_value(alice, U256::from(1)).buy(U256::from(3), "ipfs://sold".into()).unwrap();
+        assert_eq!(U256::from(4), contract.sender(alice).next_token_id());
+        assert_eq!(U256::from(4), contract.sender(alice).mint(alice, "ipfs://next".into()).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_pause_until(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).mint(alice, "ipfs://a".into()).unwrap();
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).pause_until(now + U256::from(60)).unwrap_err());
+        contract.sender(alice).pause_until(now + U256::from(60)).unwrap();
+        assert!(contract.sender(alice).paused());
+        assert_eq!(b"Paused".to_vec(), contract.sender(alice).mint(alice, "ipfs://b".into()).unwrap_err());
+        assert_eq!(b"Paused".to_vec(), contract.sender(alice).batch_mint(alice, uris(1)).unwrap_err());
+        assert_eq!(b"Paused".to_vec(), contract.sender(alice).transfer_from(alice, bob, U256::ZERO).unwrap_err());
+
+        // The test VM clock doesn't advance, so move the expiry to now instead.
+        contract.sender(alice).pause_expiry.set(now);
+        assert!(!contract.sender(alice).paused());
+        contract.sender(alice).transfer_from(alice, bob, U256::ZERO).unwrap();
+        contract.sender(alice).mint(alice, "ipfs://b".into()).unwrap();
+    }
+
+    #[motsu::test]
+    fn test_unpause_clears_pause(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+
+        contract.sender(alice).pause().unwrap();
+        contract.sender(alice).pause_until(now + U256::from(60)).unwrap();
+        assert_eq!(b"Paused".to_vec(), contract.sender(bob).mint(bob, "ipfs://a".into()).unwrap_err());
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).unpause().unwrap_err());
+        contract.sender(alice).unpause().unwrap();
+        assert!(!contract.sender(alice).paused());
+        assert_eq!(U256::ZERO, contract.sender(alice).pause_expiry());
+        contract.sender(bob).mint(bob, "ipfs://a".into()).unwrap();
+    }
+
+    #[motsu::test]
+    fn test_metadata_mode(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(8)).unwrap();
+        let token_id = U256::from(7);
+        assert_eq!(b"Invalid mode".to_vec(), contract.sender(alice).set_metadata_mode(3).unwrap_err());
+
+        contract.sender(alice).set_metadata_mode(METADATA_MODE_SVG).unwrap();
+        let uri = contract.sender(alice).token_uri(token_id).unwrap();
+        let svg = BASE64.decode(uri.strip_prefix("data:image/svg+xml;base64,").unwrap()).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<svg") && svg.contains("DEMO #7"));
+
+        contract.sender(alice).set_metadata_mode(METADATA_MODE_JSON).unwrap();
+        let uri = contract.sender(alice).token_uri(token_id).unwrap();
+        let json = BASE64.decode(uri.strip_prefix("data:application/json;base64,").unwrap()).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains(r#""name":"Demo #7""#));
+        assert!(json.contains(&format!(r#""image":"data:image/svg+xml;base64,{}""#, BASE64.encode(&svg))));
+
+        contract.sender(alice).set_metadata_mode(METADATA_MODE_STORED).unwrap();
+        assert_eq!("ipfs://token-7", contract.sender(alice).token_uri(token_id).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_token_royalty(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).set_royalty(bob, U256::from(500)).unwrap();
+        assert_eq!(U256::from(500), contract.sender(alice).default_royalty_bps());
+        let token_id = U256::from(3);
+
+        contract.sender(alice).set_token_royalty(token_id, carol, U256::from(1_000)).unwrap();
+        assert_eq!(U256::from(1), contract.sender(alice).custom_royalty_count());
+        assert_eq!((carol, U256::from(100)), contract.sender(alice).royalty_info(token_id, U256::from(1_000)));
+        assert_eq!((bob, U256::from(50)), contract.sender(alice).royalty_info(U256::ZERO, U256::from(1_000)));
+
+        // Overwriting an override doesn't count it twice.
+        contract.sender(alice).set_token_royalty(token_id, carol, U256::from(200)).unwrap();
+        assert_eq!(U256::from(1), contract.sender(alice).custom_royalty_count());
+        contract.sender(alice).set_token_royalty(U256::ZERO, carol, U256::from(200)).unwrap();
+        assert_eq!(U256::from(2), contract.sender(alice).custom_royalty_count());
+
+        contract.sender(alice).reset_token_royalty(token_id).unwrap();
+        contract.sender(alice).reset_token_royalty(token_id).unwrap();
+        assert_eq!(U256::from(1), contract.sender(alice).custom_royalty_count());
+        assert_eq!((bob, U256::from(50)), contract.sender(alice).royalty_info(token_id, U256::from(1_000)));
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).reset_token_royalty(U256::ZERO).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_royalty_events(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).set_royalty(bob, U256::from(500)).unwrap();
+        contract.assert_emitted(&DefaultRoyaltySet { receiver: bob, bps: U256::from(500) });
+
+        contract.sender(alice).set_token_royalty(U256::from(3), carol, U256::from(1_000)).unwrap();
+        contract.assert_emitted(&TokenRoyaltySet { tokenId: U256::from(3), receiver: carol, bps: U256::from(1_000) });
+        contract.sender(alice).batch_set_token_royalty(vec![U256::from(4)], vec![bob], vec![U256::from(200)]).unwrap();
+        contract.assert_emitted(&TokenRoyaltySet { tokenId: U256::from(4), receiver: bob, bps: U256::from(200) });
+
+        contract.sender(alice).reset_token_royalty(U256::from(3)).unwrap();
+        contract.assert_emitted(&TokenRoyaltyReset { tokenId: U256::from(3) });
+    }
+
+    #[motsu::test]
+    fn test_freeze_royalties(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).mint(alice, String::new()).unwrap();
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).freeze_royalties().unwrap_err());
+        contract.sender(alice).set_royalty(bob, U256::from(500)).unwrap();
+        contract.sender(alice).set_token_royalty(U256::ZERO, bob, U256::from(250)).unwrap();
+        assert!(!contract.sender(alice).are_royalties_frozen());
+
+        contract.sender(alice).freeze_royalties().unwrap();
+        assert!(contract.sender(alice).are_royalties_frozen());
+        assert_eq!((bob, U256::from(25)), contract.sender(alice).royalty_info(U256::ZERO, U256::from(1_000)));
+        let frozen = b"Royalties frozen".to_vec();
+        assert_eq!(frozen, contract.sender(alice).set_royalty(bob, U256::from(1_000)).unwrap_err());
+        assert_eq!(frozen, contract.sender(alice).set_token_royalty(U256::ZERO, bob, U256::from(1_000)).unwrap_err());
+        let err = contract.sender(alice).batch_set_token_royalty(vec![U256::ZERO], vec![bob], vec![U256::from(1_000)]).unwrap_err();
+        assert_eq!(frozen, err);
+        assert_eq!(frozen, contract.sender(alice).reset_token_royalty(U256::ZERO).unwrap_err());
+        assert_eq!(frozen, contract.sender(alice).set_flat_royalty(true, U256::from(1)).unwrap_err());
+        assert_eq!(frozen, contract.sender(alice).set_royalty_follows_owner(true).unwrap_err());
+    }
+
+    #[motsu::test]
+    fn test_batch_set_token_royalty(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address, dave: Address) {
+        init(&contract, alice, 0);
+        let ids = vec![U256::from(1), U256::from(2), U256::from(3)];
+        let bps = vec![U256::from(100), U256::from(250), U256::from(1_000)];
+        contract.sender(alice).batch_set_token_royalty(ids.clone(), vec![bob, carol, dave], bps.clone()).unwrap();
+
+        let price = U256::from(10_000);
+        assert_eq!((bob, U256::from(100)), contract.sender(alice).royalty_info(ids[0], price));
+        assert_eq!((carol, U256::from(250)), contract.sender(alice).royalty_info(ids[1], price));
+        assert_eq!((dave, U256::from(1_000)), contract.sender(alice).royalty_info(ids[2], price));
+        assert_eq!(U256::from(3), contract.sender(alice).custom_royalty_count());
+
+        let err = contract.sender(alice).batch_set_token_royalty(ids.clone(), vec![bob, carol], bps.clone()).unwrap_err();
+        assert_eq!(b"Length mismatch".to_vec(), err);
+        let err = contract.sender(bob).batch_set_token_royalty(ids, vec![bob, carol, dave], bps).unwrap_err();
+        assert_eq!(b"Not owner".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_freeze_base_uri(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).mint(alice, "".into()).unwrap();
+        contract.sender(alice).mint(alice, "ipfs://own".into()).unwrap();
+        assert_eq!("ipfs://0", contract.sender(alice).token_uri(U256::ZERO).unwrap());
+
+        contract.sender(alice).set_base_uri("https://example.com/meta/".into()).unwrap();
+        contract.sender(alice).set_uri_suffix(".json".into()).unwrap();
+        assert_eq!("https://example.com/meta/0.json", contract.sender(alice).token_uri(U256::ZERO).unwrap());
+        assert_eq!("ipfs://own", contract.sender(alice).token_uri(U256::from(1)).unwrap());
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).freeze_base_uri().unwrap_err());
+        contract.sender(alice).freeze_base_uri().unwrap();
+        assert!(contract.sender(alice).base_uri_frozen());
+        assert_eq!(b"Base URI frozen".to_vec(), contract.sender(alice).set_base_uri("ipfs://new/".into()).unwrap_err());
+        assert_eq!(b"Base URI frozen".to_vec(), contract.sender(alice).set_uri_suffix("".into()).unwrap_err());
+
+        // Per-token edits aren't covered by this freeze.
+        contract.sender(alice).set_token_uri(U256::from(1), "ipfs://edited".into()).unwrap();
+        contract.assert_emitted(&MetadataUpdate { _tokenId: U256::from(1) });
+        assert_eq!("ipfs://edited", contract.sender(alice).token_uri(U256::from(1)).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_minted_at(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        let token_id = contract.sender(alice).mint(alice, "ipfs://a".into()).unwrap();
+        let batch_id = contract.sender(alice).batch_mint(alice, uris(2)).unwrap() + U256::from(1);
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+        assert_eq!(now, contract.sender(alice).minted_at(token_id).unwrap());
+        assert_eq!(now, contract.sender(alice).minted_at(batch_id).unwrap());
+
+        // Transfers don't move the mint time.
+        contract.sender(alice).minted_at.setter(token_id).set(now - U256::from(60));
+        contract.sender(alice).transfer_from(alice, bob, token_id).unwrap();
+        assert_eq!(now - U256::from(60), contract.sender(alice).minted_at(token_id).unwrap());
+
+        let err = contract.sender(alice).minted_at(U256::from(10)).unwrap_err();
+        assert_eq!(NonexistentToken { id: U256::from(10) }.abi_encode(), err);
+    }
+
+    #[motsu::test]
+    fn test_timed_lock(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(1)).unwrap();
+        assert_eq!(b"Not token owner".to_vec(), contract.sender(bob).lock_for(U256::ZERO, U256::from(60)).unwrap_err());
+
+        contract.sender(alice).lock_for(U256::ZERO, U256::from(60)).unwrap();
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+        assert_eq!(now + U256::from(60), contract.sender(alice).lock_until(U256::ZERO));
+        assert!(contract.sender(alice).is_locked(U256::ZERO));
+        assert_eq!(b"Token locked".to_vec(), contract.sender(alice).lock_for(U256::ZERO, U256::ZERO).unwrap_err());
+        assert_eq!(b"Token locked".to_vec(), contract.sender(alice).transfer_from(alice, bob, U256::ZERO).unwrap_err());
+
+        // Pretend the lock ran out.
+        contract.sender(alice).lock_until.setter(U256::ZERO).set(now);
+        assert!(!contract.sender(alice).is_locked(U256::ZERO));
+        contract.sender(alice).transfer_from(alice, bob, U256::ZERO).unwrap();
+    }
+
+    #[motsu::test]
+    fn test_token_seed(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        let first = contract.sender(alice).mint(bob, String::new()).unwrap();
+        contract.sender(alice).batch_mint(bob, uris(2)).unwrap();
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+
+        let seeds: Vec<_> = (0..3).map(|i| contract.sender(alice).token_seed(first + U256::from(i)).unwrap()).collect();
+        assert_eq!(keccak((now, alice, first).abi_encode()), seeds[0]);
+        assert_eq!(keccak((now, alice, U256::from(2)).abi_encode()), seeds[2]);
+        assert!(seeds[0] != seeds[1] && seeds[1] != seeds[2] && seeds[0] != seeds[2]);
+        assert!(contract.sender(alice).token_seed(U256::from(3)).is_err());
+    }
+
+    #[motsu::test]
+    fn test_min_hold_seconds(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_min_hold_seconds(U256::from(60)).unwrap_err());
+        contract.sender(alice).set_min_hold_seconds(U256::from(60)).unwrap();
+        let token_id = contract.sender(alice).mint(alice, "ipfs://a".into()).unwrap();
+        let batch_id = contract.sender(alice).batch_mint(alice, uris(2)).unwrap() + U256::from(1);
+        let now = U256::from(contract.sender(alice).vm().block_timestamp());
+        assert_eq!(now, contract.sender(alice).acquired_at(batch_id));
+
+        for id in [token_id, batch_id] {
+            let err = contract.sender(alice).transfer_from(alice, bob, id).unwrap_err();
+            assert_eq!(b"Hold period active".to_vec(), err);
+        }
+
+        // Pretend the token was minted a minute ago.
+        contract.sender(alice).acquired_at.setter(token_id).set(now - U256::from(60));
+        contract.sender(alice).transfer_from(alice, bob, token_id).unwrap();
+        // Receiving it restarts the hold period.
+        let err = contract.sender(bob).transfer_from(bob, alice, token_id).unwrap_err();
+        assert_eq!(b"Hold period active".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_transfer_fee(contract: Contract<DEMONFT>, treasury: Contract<EthReceiver>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        alice.fund(U256::from(1_000));
+        bob.fund(U256::from(1_000));
+        assert_eq!(b"No treasury".to_vec(), contract.sender(alice).set_transfer_fee(U256::from(10)).unwrap_err());
+        let err = contract.sender_and_value(alice, U256::from(10)).transfer_from(alice, bob, U256::ZERO).unwrap_err();
+        assert_eq!(b"No treasury".to_vec(), err);
+        contract.sender(alice).set_treasury(treasury.address(), false).unwrap();
+
+        // Free transfers work as before.
+        contract.sender(alice).transfer_from(alice, bob, U256::ZERO).unwrap();
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_transfer_fee(U256::from(10)).unwrap_err());
+        contract.sender(alice).set_transfer_fee(U256::from(10)).unwrap();
+        contract.sender_and_value(alice, U256::from(10)).transfer_from(alice, bob, U256::from(1)).unwrap();
+        contract.sender_and_value(alice, U256::from(15)).safe_transfer_from(alice, bob, U256::from(2)).unwrap();
+        assert_eq!(U256::from(25), treasury.balance());
+        assert_eq!(U256::from(3), contract.sender(alice).balance_of(bob).unwrap());
+
+        assert_eq!(b"Zero address".to_vec(), contract.sender(alice).set_treasury(Address::ZERO, false).unwrap_err());
+        let err = contract.sender_and_value(bob, U256::from(9)).transfer_from(bob, alice, U256::ZERO).unwrap_err();
+        assert_eq!(b"Insufficient transfer fee".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_transfer_validator(contract: Contract<DEMONFT>, validator: Contract<MockValidator>, alice: Address, bob: Address, carol: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(2)).unwrap();
+        validator.sender(alice).deny(carol);
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_transfer_validator(validator.address()).unwrap_err());
+        contract.sender(alice).set_transfer_validator(validator.address()).unwrap();
+
+        contract.sender(alice).transfer_from(alice, bob, U256::ZERO).unwrap();
+        let err = contract.sender(alice).transfer_from(alice, carol, U256::from(1)).unwrap_err();
+        assert_eq!(b"Transfer denied".to_vec(), err);
+        let err = contract.sender(alice).safe_transfer_from(alice, carol, U256::from(1)).unwrap_err();
+        assert_eq!(b"Transfer denied".to_vec(), err);
+
+        contract.sender(alice).set_transfer_validator(Address::ZERO).unwrap();
+        contract.sender(alice).transfer_from(alice, carol, U256::from(1)).unwrap();
+    }
+
+    #[motsu::test]
+    fn test_operator_filter(
+        contract: Contract<DEMONFT>,
+        registry: Contract<MockRegistry>,
+        alice: Address,
+        bob: Address,
+        carol: Address,
+        dave: Address,
+    ) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, uris(3)).unwrap();
+        contract.sender(alice).set_approval_for_all(bob, true).unwrap();
+        contract.sender(alice).set_approval_for_all(carol, true).unwrap();
+        registry.sender(alice).block(carol);
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).register_operator_filter(registry.address(), dave).unwrap_err());
+        contract.sender(alice).register_operator_filter(registry.address(), dave).unwrap();
+        assert_eq!(contract.address(), registry.sender(alice).registrant.get());
+        assert_eq!(dave, registry.sender(alice).subscription.get());
+        assert_eq!((registry.address(), true), contract.sender(alice).operator_filter());
+
+        contract.sender(bob).transfer_from(alice, dave, U256::ZERO).unwrap();
+        contract.sender(alice).transfer_from(alice, dave, U256::from(1)).unwrap();
+
+        contract.sender(alice).set_operator_filter_enabled(false).unwrap();
+        contract.sender(carol).transfer_from(alice, dave, U256::from(2)).unwrap();
+        contract.sender(dave).set_approval_for_all(carol, true).unwrap();
+
+        contract.sender(alice).set_operator_filter_enabled(true).unwrap();
+        let err = contract.sender(carol).transfer_from(dave, alice, U256::from(2)).unwrap_err();
+        assert_eq!(b"Operator not allowed".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_migrate_uris(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).token_uris_str.set_str("ipfs://0\nipfs://1\nipfs://2\nipfs://3\nipfs://4\n");
+        contract.sender(alice).batch_mint(alice, vec![String::new(); 5]).unwrap();
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).migrate_uris(U256::ZERO, U256::from(3)).unwrap_err());
+
+        contract.sender(alice).migrate_uris(U256::ZERO, U256::from(3)).unwrap();
+        assert!(contract.sender(alice).token_uris.get(U256::from(3)).is_empty());
+        contract.sender(alice).set_token_uri(U256::from(1), "ipfs://edited".into()).unwrap();
+        contract.sender(alice).migrate_uris(U256::ZERO, U256::from(3)).unwrap();
+        contract.sender(alice).migrate_uris(U256::from(3), U256::from(3)).unwrap();
+
+        // The legacy list is no longer consulted once the map is populated.
+        contract.sender(alice).token_uris_str.set_str("");
+        for (id, expected) in [(0, "ipfs://0"), (1, "ipfs://edited"), (2, "ipfs://2"), (3, "ipfs://3"), (4, "ipfs://4")] {
+            assert_eq!(expected, contract.sender(alice).token_uris.get(U256::from(id)).get_string());
+            assert_eq!(expected, contract.sender(alice).token_uri(U256::from(id)).unwrap());
+        }
+    }
+
+    #[motsu::test]
+    fn test_legacy_ownership(contract: Contract<DEMONFT>, alice: Address, bob: Address, carol: Address, dave: Address) {
+        init(&contract, alice, 0);
+        // Recreate a deployment from before ownership moved out of `erc721`.
+        contract.sender(alice).storage_version.set(U256::ZERO);
+        for (id, to) in [(0, bob), (1, bob), (2, carol)] {
+            contract.sender(alice).erc721._mint(to, U256::from(id)).unwrap();
+        }
+        contract.sender(alice).next_id.set(U256::from(3));
+        contract.sender(alice).token_uris_str.set_str("ipfs://0\nipfs://1\nipfs://2");
+
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::from(1)).unwrap());
+        assert_eq!(carol, contract.sender(alice).owner_of(U256::from(2)).unwrap());
+        assert_eq!(U256::from(2), contract.sender(alice).balance_of(bob).unwrap());
+        contract.sender(alice).migrate_uris(U256::ZERO, U256::from(3)).unwrap();
+        assert_eq!("ipfs://1", contract.sender(alice).token_uri(U256::from(1)).unwrap());
+        contract.sender(alice).set_storage_version(STORAGE_VERSION).unwrap();
+        assert_eq!(U256::from(3), contract.sender(alice).total_minted());
+
+        contract.sender(bob).transfer_from(bob, dave, U256::ZERO).unwrap();
+        assert_eq!(dave, contract.sender(alice).owner_of(U256::ZERO).unwrap());
+        assert_eq!(bob, contract.sender(alice).owner_of(U256::from(1)).unwrap());
+        assert_eq!(U256::from(1), contract.sender(alice).balance_of(bob).unwrap());
+        assert_eq!(U256::from(1), contract.sender(alice).balance_of(dave).unwrap());
+
+        assert_eq!(U256::from(3), contract.sender(alice).batch_mint(carol, uris(2)).unwrap());
+        assert_eq!(carol, contract.sender(alice).owner_of(U256::from(4)).unwrap());
+        assert_eq!(U256::from(3), contract.sender(alice).balance_of(carol).unwrap());
+        assert_eq!(U256::from(5), contract.sender(alice).total_minted());
+
+        contract.sender(bob).burn(U256::from(1)).unwrap();
+        assert!(contract.sender(alice).owner_of(U256::from(1)).is_err());
+        assert_eq!(U256::ZERO, contract.sender(alice).balance_of(bob).unwrap());
+    }
+
+    #[motsu::test]
+    fn test_migrate_uris_bounds(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).token_uris_str.set_str("ipfs://0\nipfs://1\nipfs://2\n");
+        contract.sender(alice).batch_mint(alice, vec![String::new(); 2]).unwrap();
+
+        // Only minted ids are migrated.
+        contract.sender(alice).migrate_uris(U256::ZERO, U256::from(3)).unwrap();
+        assert_eq!("ipfs://1", contract.sender(alice).token_uris.get(U256::from(1)).get_string());
+        assert!(contract.sender(alice).token_uris.get(U256::from(2)).is_empty());
+
+        let err = contract.sender(alice).migrate_uris(U256::MAX, U256::from(1)).unwrap_err();
+        assert_eq!(b"Invalid range".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_token_uri_raw(contract: Contract<DEMONFT>, alice: Address, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).batch_mint(alice, vec!["ipfs://a".into(), String::new()]).unwrap();
+        let binary = vec![0x1f, 0x8b, 0xff, 0x00, 0xc3];
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_token_uri_bytes(U256::ZERO, binary.clone().into()).unwrap_err());
+        contract.sender(alice).set_token_uri_bytes(U256::ZERO, binary.clone().into()).unwrap();
+
+        assert_eq!(Bytes::from(binary.clone()), contract.sender(alice).token_uri_raw(U256::ZERO).unwrap());
+        assert_ne!(vec![0x1f, 0x8b, 0xff, 0x00, 0xc3], contract.sender(alice).token_uri(U256::ZERO).unwrap().into_bytes());
+        assert_eq!(Bytes::from(b"ipfs://1".to_vec()), contract.sender(alice).token_uri_raw(U256::from(1)).unwrap());
+        assert!(contract.sender(alice).token_uri_raw(U256::from(2)).is_err());
+        assert!(contract.sender(alice).set_token_uri_bytes(U256::from(2), binary.clone().into()).is_err());
+
+        contract.sender(alice).set_max_uri_length(U256::from(4)).unwrap();
+        let err = contract.sender(alice).set_token_uri_bytes(U256::ZERO, binary.into()).unwrap_err();
+        assert_eq!(b"URI too long".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_token_http_uri(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).mint(alice, "ipfs://bafycid/1.json".into()).unwrap();
+        contract.sender(alice).mint(alice, "https://example.com/2.json".into()).unwrap();
+        assert_eq!("ipfs://bafycid/1.json", contract.sender(alice).token_http_uri(U256::ZERO).unwrap());
+
+        contract.sender(alice).set_ipfs_gateway("https://ipfs.io/".into()).unwrap();
+        assert_eq!("https://ipfs.io/ipfs/bafycid/1.json", contract.sender(alice).token_http_uri(U256::ZERO).unwrap());
+        assert_eq!("https://example.com/2.json", contract.sender(alice).token_http_uri(U256::from(1)).unwrap());
+        assert_eq!("ipfs://bafycid/1.json", contract.sender(alice).token_uri(U256::ZERO).unwrap());
+        assert!(contract.sender(alice).token_http_uri(U256::from(2)).is_err());
+    }
+
+    #[motsu::test]
+    fn test_every_mint_path_respects_max_supply(contract: Contract<DEMONFT>, partner: Contract<Erc721>, alice: Address, bob: Address) {
+        init(&contract, alice, 1);
+        contract.sender(alice).grant_credits(bob, U256::from(1)).unwrap();
+        contract.sender(alice).set_free_allocation(bob, U256::from(1)).unwrap();
+        contract.sender(alice).set_token_price(U256::from(5), U256::from(1)).unwrap();
+        contract.sender(alice).set_redemption_collection(partner.address()).unwrap();
+        partner.sender(alice)._mint(bob, U256::from(7)).unwrap();
+        partner.sender(bob).approve(contract.address(), U256::from(7)).unwrap();
+        bob.fund(U256::from(1));
+
+        contract.sender(alice).mint(alice, "ipfs://last".into()).unwrap();
+
+        let is_cap = |err: Vec<u8>| MaxSupplyReached::abi_decode(&err, true).is_ok();
+        assert!(is_cap(contract.sender(bob).mint(bob, "ipfs://x".into()).unwrap_err()));
+        assert!(is_cap(contract.sender(bob).batch_mint(bob, uris(1)).unwrap_err()));
+        assert!(is_cap(contract.sender(bob).mint_with_credit("ipfs://x".into()).unwrap_err()));
+        assert!(is_cap(contract.sender(bob).free_mint("ipfs://x".into()).unwrap_err()));
+        assert!(is_cap(contract.sender_and_value(bob, U256::from(1)).buy(U256::from(5), "ipfs://x".into()).unwrap_err()));
+        assert!(is_cap(contract.sender(bob).mint_deterministic(bob, FixedBytes::ZERO, "ipfs://x".into()).unwrap_err()));
+        assert!(is_cap(contract.sender(bob).claim_by_burning(U256::from(7), "ipfs://x".into()).unwrap_err()));
+        assert_eq!(U256::from(1), contract.sender(alice).total_minted());
+    }
+
+    #[motsu::test]
+    fn test_eip712_domain(contract: Contract<DEMONFT>, alice: Address) {
+        init(&contract, alice, 0);
+        let (fields, name, version, chain_id, verifying_contract, salt, extensions) = contract.sender(alice).eip712_domain();
+        assert_eq!(FixedBytes::from([0x0f]), fields);
+        assert_eq!("DemoNFT", name);
+        assert_eq!("1", version);
+        assert_eq!(contract.address(), verifying_contract);
+        assert_eq!(B256::ZERO, salt);
+        assert!(extensions.is_empty());
+
+        let type_hash = keccak(b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)");
+        let separator = keccak((type_hash, keccak(name.as_bytes()), keccak(version.as_bytes()), chain_id, verifying_contract).abi_encode());
+        assert_eq!(separator, contract.sender(alice).domain_separator());
+    }
+
+    #[motsu::test]
+    fn test_rotate_allow_signer(contract: Contract<DEMONFT>, alice: Address, old_signer: Account, new_signer: Account, bob: Address) {
+        init(&contract, alice, 0);
+        let hash = contract.sender(bob).allowlist_mint_hash(bob);
+        let sign = |signer: &Account| -> Bytes { signer.signer().sign_hash_sync(&hash).unwrap().as_bytes().to_vec().into() };
+
+        let err = contract.sender(bob).signed_allowlist_mint("ipfs://a".into(), sign(&old_signer)).unwrap_err();
+        assert_eq!(b"Signed allowlist disabled".to_vec(), err);
+        contract.sender(alice).set_allow_signer(old_signer.address()).unwrap();
+        contract.assert_emitted(&AllowSignerRotated { old_signer: Address::ZERO, new_signer: old_signer.address() });
+
+        assert_eq!(b"Not owner".to_vec(), contract.sender(bob).set_allow_signer(bob).unwrap_err());
+        contract.sender(alice).set_allow_signer(new_signer.address()).unwrap();
+        contract.assert_emitted(&AllowSignerRotated { old_signer: old_signer.address(), new_signer: new_signer.address() });
+
+        let err = contract.sender(bob).signed_allowlist_mint("ipfs://a".into(), sign(&old_signer)).unwrap_err();
+        assert_eq!(b"Invalid signature".to_vec(), err);
+        let token_id = contract.sender(bob).signed_allowlist_mint("ipfs://a".into(), sign(&new_signer)).unwrap();
+        assert_eq!(bob, contract.sender(alice).owner_of(token_id).unwrap());
+        assert_eq!(U256::from(1), contract.sender(alice).allowlist_claimed(bob));
+
+        let err = contract.sender(bob).signed_allowlist_mint("ipfs://b".into(), sign(&new_signer)).unwrap_err();
+        assert_eq!(b"Already claimed".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_redeem_voucher(contract: Contract<DEMONFT>, alice: Address, signer: Account, bob: Address) {
+        init(&contract, alice, 0);
+        let uri = String::from("ipfs://voucher");
+        let hash = contract.sender(bob).voucher_hash(bob, uri.clone(), U256::from(1));
+        let signature: Bytes = signer.signer().sign_hash_sync(&hash).unwrap().as_bytes().to_vec().into();
+
+        let err = contract.sender(bob).redeem(bob, uri.clone(), U256::from(1), signature.clone()).unwrap_err();
+        assert_eq!(b"Vouchers disabled".to_vec(), err);
+        contract.sender(alice).set_voucher_signer(signer.address()).unwrap();
+
+        // The signature only covers the exact voucher.
+        let err = contract.sender(bob).redeem(bob, uri.clone(), U256::from(2), signature.clone()).unwrap_err();
+        assert_eq!(b"Invalid signature".to_vec(), err);
+
+        assert!(!contract.sender(bob).is_voucher_used(hash));
+        let token_id = contract.sender(bob).redeem(bob, uri.clone(), U256::from(1), signature.clone()).unwrap();
+        assert_eq!(bob, contract.sender(bob).owner_of(token_id).unwrap());
+        assert!(contract.sender(bob).is_voucher_used(hash));
+
+        let err = contract.sender(bob).redeem(bob, uri, U256::from(1), signature).unwrap_err();
+        assert_eq!(b"Voucher used".to_vec(), err);
+    }
+
+    #[motsu::test]
+    fn test_signatures_bound_to_chain(contract: Contract<DEMONFT>, alice: Address, signer: Account, bob: Address) {
+        init(&contract, alice, 0);
+        contract.sender(alice).set_voucher_signer(signer.address()).unwrap();
+        let uri = String::from("ipfs://voucher");
+        let hash = contract.sender(bob).voucher_hash(bob, uri.clone(), U256::ZERO);
+        let signature: Bytes = signer.signer().sign_hash_sync(&hash).unwrap().as_bytes().to_vec().into();
+        let chain_id = contract.sender(bob).eip712_domain().3;
+
+        // The same deployment on another chain.
+        CHAIN_ID.set(Some(chain_id + U256::from(1)));
+        assert_eq!(chain_id + U256::from(1), contract.sender(bob).eip712_domain().3);
+        let err = contract.sender(bob).redeem(bob, uri.clone(), U256::ZERO, signature.clone()).unwrap_err();
+        assert_eq!(b"Invalid signature".to_vec(), err);
+
+        CHAIN_ID.set(None);
+        contract.sender(bob).redeem(bob, uri, U256::ZERO, signature).unwrap();
     }
 }